@@ -0,0 +1,256 @@
+use super::checkdigit::{CheckDigitScheme, DetectableError};
+
+use rand_pcg::Pcg64;
+use rand_seeder::rand_core::RngCore;
+use rand_seeder::Seeder;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::fmt;
+
+const DIGITS: &str = "0123456789";
+
+const ERROR_CLASSES: [DetectableError; 5] = [
+    DetectableError::SingleSubstitution,
+    DetectableError::AdjacentTransposition,
+    DetectableError::JumpTransposition,
+    DetectableError::TwinError,
+    DetectableError::PhoneticError,
+];
+
+#[derive(Debug, PartialEq)]
+pub enum AnalysisError {
+    /// Exhaustive coverage of payloads this long would have more than `u64::MAX` codewords.
+    PayloadTooLong(usize),
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Which codewords to apply errors to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Coverage {
+    /// Every payload of the given length. There are 10^length of these, so keep it short.
+    Exhaustive,
+    /// The given number of random payloads, drawn from a generator seeded with `seed`.
+    Sampled { codewords: usize, seed: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorClassResult {
+    pub error: DetectableError,
+    pub tested: u64,
+    pub detected: u64,
+}
+
+impl ErrorClassResult {
+    /// Percentage of the tested errors the scheme detected. Error classes that couldn't occur in
+    /// any codeword (e.g. twin errors in codewords without a repeated digit) report 100%.
+    pub fn percentage(&self) -> f64 {
+        if self.tested == 0 {
+            100.0
+        } else {
+            self.detected as f64 * 100.0 / self.tested as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectionReport {
+    pub scheme: &'static str,
+    pub payload_length: usize,
+    pub codewords: u64,
+    pub results: Vec<ErrorClassResult>,
+}
+
+impl DetectionReport {
+    pub fn result(&self, error: DetectableError) -> &ErrorClassResult {
+        self.results.iter().find(|result| result.error == error).unwrap()
+    }
+}
+
+/// Applies every error of the given class to `codeword`, returning the altered codewords. Errors
+/// only ever replace digits with other digits.
+fn apply_errors(codeword: &[char], error: DetectableError) -> Vec<Vec<char>> {
+    let mut altered = Vec::new();
+    let n = codeword.len();
+
+    match error {
+        DetectableError::SingleSubstitution => {
+            for i in 0..n {
+                for c in DIGITS.chars().filter(|&c| c != codeword[i]) {
+                    let mut word = codeword.to_vec();
+                    word[i] = c;
+                    altered.push(word);
+                }
+            }
+        }
+        DetectableError::AdjacentTransposition | DetectableError::JumpTransposition => {
+            let gap = if error == DetectableError::AdjacentTransposition { 1 } else { 2 };
+            for i in 0..n.saturating_sub(gap) {
+                if codeword[i] != codeword[i + gap] {
+                    let mut word = codeword.to_vec();
+                    word.swap(i, i + gap);
+                    altered.push(word);
+                }
+            }
+        }
+        DetectableError::TwinError => {
+            for i in 0..n.saturating_sub(1) {
+                if codeword[i] == codeword[i + 1] {
+                    for c in DIGITS.chars().filter(|&c| c != codeword[i]) {
+                        let mut word = codeword.to_vec();
+                        word[i] = c;
+                        word[i + 1] = c;
+                        altered.push(word);
+                    }
+                }
+            }
+        }
+        DetectableError::PhoneticError => {
+            // "twelve" heard as "twenty" and vice versa, up to 19 and 90: a0 <-> 1a for a = 2..9
+            for i in 0..n.saturating_sub(1) {
+                let pair = (codeword[i], codeword[i + 1]);
+                let replacement = match pair {
+                    ('1', d) if ('2'..='9').contains(&d) => Some((d, '0')),
+                    (d, '0') if ('2'..='9').contains(&d) => Some(('1', d)),
+                    _ => None,
+                };
+                if let Some((first, second)) = replacement {
+                    let mut word = codeword.to_vec();
+                    word[i] = first;
+                    word[i + 1] = second;
+                    altered.push(word);
+                }
+            }
+        }
+    }
+
+    altered
+}
+
+/// Measures how many errors of each class a check digit scheme detects, by applying every possible
+/// error of that class to a set of valid codewords and counting how many fail verification.
+/// Payloads are made of decimal digits; ones the scheme can't encode are skipped.
+pub fn analyse(
+    scheme: &dyn CheckDigitScheme,
+    payload_length: usize,
+    coverage: Coverage,
+) -> Result<DetectionReport, AnalysisError> {
+    let payloads: Box<dyn Iterator<Item = String>> = match coverage {
+        Coverage::Exhaustive => {
+            let count = u32::try_from(payload_length)
+                .ok()
+                .and_then(|length| 10u64.checked_pow(length))
+                .ok_or(AnalysisError::PayloadTooLong(payload_length))?;
+            Box::new((0..count).map(move |n| format!("{:0width$}", n, width = payload_length)))
+        }
+        Coverage::Sampled { codewords, seed } => {
+            let mut rng: Pcg64 = Seeder::from(seed).make_rng();
+            Box::new((0..codewords).map(move |_| {
+                (0..payload_length)
+                    .map(|_| std::char::from_digit((rng.next_u32() % 10) as u32, 10).unwrap())
+                    .collect()
+            }))
+        }
+    };
+
+    let mut results: Vec<ErrorClassResult> = ERROR_CLASSES
+        .iter()
+        .map(|&error| ErrorClassResult {
+            error,
+            tested: 0,
+            detected: 0,
+        })
+        .collect();
+    let mut codewords = 0;
+
+    for payload in payloads {
+        let check = match scheme.compute(&payload) {
+            Ok(check) => check,
+            Err(_) => continue,
+        };
+        let codeword: Vec<char> = payload.chars().chain(check.chars()).collect();
+        codewords += 1;
+
+        for result in results.iter_mut() {
+            for altered in apply_errors(&codeword, result.error) {
+                result.tested += 1;
+                if scheme.verify(&altered.into_iter().collect::<String>()).is_err() {
+                    result.detected += 1;
+                }
+            }
+        }
+    }
+
+    Ok(DetectionReport {
+        scheme: scheme.name(),
+        payload_length,
+        codewords,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkdigit::*;
+
+    #[test]
+    pub fn guaranteed_detection_holds() {
+        let cases: [(&dyn CheckDigitScheme, usize, Coverage); 9] = [
+            (&Luhn, 3, Coverage::Exhaustive),
+            (&Isbn10, 9, Coverage::Sampled { codewords: 500, seed: 1 }),
+            (&Ean13, 12, Coverage::Sampled { codewords: 500, seed: 2 }),
+            (&UpcA, 11, Coverage::Sampled { codewords: 500, seed: 3 }),
+            (&Verhoeff, 3, Coverage::Exhaustive),
+            (&Damm, 3, Coverage::Exhaustive),
+            (&Iso7064Mod97, 3, Coverage::Exhaustive),
+            (&Iso7064Mod11, 3, Coverage::Exhaustive),
+            (&Hamming, 6, Coverage::Sampled { codewords: 500, seed: 4 }),
+        ];
+
+        for (scheme, payload_length, coverage) in cases.iter() {
+            let report = analyse(*scheme, *payload_length, *coverage).unwrap();
+            assert!(report.codewords > 0);
+            for &error in scheme.detects().iter() {
+                let result = report.result(error);
+                assert!(result.tested > 0, "{} {:?}", scheme.name(), error);
+                assert_eq!(result.detected, result.tested, "{} {:?}", scheme.name(), error);
+            }
+        }
+    }
+
+    #[test]
+    pub fn luhn_misses_zero_nine_transpositions() {
+        let report = analyse(&Luhn, 3, Coverage::Exhaustive).unwrap();
+        assert_eq!(report.codewords, 1000);
+        assert_eq!(report.result(DetectableError::SingleSubstitution).percentage(), 100.0);
+
+        let transpositions = report.result(DetectableError::AdjacentTransposition);
+        assert!(transpositions.detected < transpositions.tested);
+        assert!(transpositions.percentage() > 95.0);
+    }
+
+    #[test]
+    pub fn exhaustive_length_limit() {
+        assert_eq!(
+            analyse(&Luhn, 20, Coverage::Exhaustive),
+            Err(AnalysisError::PayloadTooLong(20))
+        );
+        let sampled = analyse(&Luhn, 20, Coverage::Sampled { codewords: 10, seed: 5 }).unwrap();
+        assert_eq!(sampled.codewords, 10);
+    }
+
+    #[test]
+    pub fn phonetic_errors_include_twelve() {
+        let codeword: Vec<char> = "1220".chars().collect();
+        let altered: Vec<String> = apply_errors(&codeword, DetectableError::PhoneticError)
+            .into_iter()
+            .map(|word| word.into_iter().collect())
+            .collect();
+        assert_eq!(altered, vec!["2020", "1212"]);
+    }
+}
@@ -0,0 +1,240 @@
+use super::credit::{identify_card, luhn_check_digit, luhn_sum, CardScheme, IIN_RANGES};
+
+use rand_pcg::Pcg64;
+use rand_seeder::rand_core::RngCore;
+use rand_seeder::Seeder;
+use serde::Serialize;
+
+/// Longest card number allowed by ISO/IEC 7812.
+const MAX_CARD_LENGTH: usize = 19;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum CardGenerationError {
+    InvalidPrefix,
+    InvalidLength(usize),
+    UnknownScheme,
+}
+
+impl std::fmt::Display for CardGenerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardGenerationError::InvalidPrefix => {
+                write!(f, "Card generation error: The IIN prefix must only contain digits")
+            }
+            CardGenerationError::InvalidLength(length) => write!(
+                f,
+                "Card generation error: A length of {} can't hold the prefix and a check digit, or is \
+                longer than {} digits",
+                length, MAX_CARD_LENGTH
+            ),
+            CardGenerationError::UnknownScheme => {
+                write!(f, "Card generation error: The scheme has no ranges in the IIN table")
+            }
+        }
+    }
+}
+
+/// The kind of typing mistake to introduce when deliberately generating an invalid number.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum ErrorClass {
+    SingleDigit,
+    AdjacentTransposition,
+}
+
+/// Generates Luhn-valid card numbers for testing. Generators created from the same seed produce the
+/// same sequence of numbers.
+pub struct CardGenerator {
+    rng: Pcg64,
+}
+
+impl CardGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Seeder::from(seed).make_rng(),
+        }
+    }
+
+    fn random_below(&mut self, bound: usize) -> usize {
+        (self.rng.next_u64() % bound as u64) as usize
+    }
+
+    /// Generates a number of the given length starting with `prefix`, with random digits up to
+    /// the Luhn check digit.
+    pub fn generate(&mut self, prefix: &str, length: usize) -> Result<String, CardGenerationError> {
+        let mut digits = prefix
+            .chars()
+            .map(|c| c.to_digit(10))
+            .collect::<Option<Vec<u32>>>()
+            .ok_or(CardGenerationError::InvalidPrefix)?;
+
+        if length <= digits.len() || length > MAX_CARD_LENGTH {
+            return Err(CardGenerationError::InvalidLength(length));
+        }
+
+        while digits.len() < length - 1 {
+            let digit = self.random_below(10) as u32;
+            digits.push(digit);
+        }
+        digits.push(luhn_check_digit(&digits));
+
+        Ok(digits_to_string(&digits))
+    }
+
+    /// Generates a number for the given scheme, picking an IIN and a legal length from the IIN table.
+    /// The result passes `verify_credit_card` and is identified as `scheme`.
+    pub fn generate_for_scheme(&mut self, scheme: CardScheme) -> Result<String, CardGenerationError> {
+        let ranges: Vec<_> = IIN_RANGES.iter().filter(|range| range.scheme == scheme).collect();
+        if ranges.is_empty() {
+            return Err(CardGenerationError::UnknownScheme);
+        }
+
+        // Broad ranges such as Visa's "4" contain more specific ranges belonging to other schemes,
+        // so keep drawing until the number identifies as the scheme that was asked for.
+        loop {
+            let range = ranges[self.random_below(ranges.len())];
+            let iin = range.low + self.random_below((range.high - range.low + 1) as usize) as u32;
+            let length = range.lengths[self.random_below(range.lengths.len())];
+            let number = self.generate(&iin.to_string(), length)?;
+            if identify_card(&number).scheme == scheme {
+                return Ok(number);
+            }
+        }
+    }
+
+    pub fn generate_bulk(
+        &mut self,
+        prefix: &str,
+        length: usize,
+        count: usize,
+    ) -> Result<Vec<String>, CardGenerationError> {
+        (0..count).map(|_| self.generate(prefix, length)).collect()
+    }
+
+    pub fn generate_bulk_for_scheme(
+        &mut self,
+        scheme: CardScheme,
+        count: usize,
+    ) -> Result<Vec<String>, CardGenerationError> {
+        (0..count).map(|_| self.generate_for_scheme(scheme)).collect()
+    }
+
+    /// Generates a number that fails the Luhn check because of a single error of the given class.
+    /// The prefix is left untouched, so the number still identifies as the same scheme.
+    pub fn generate_invalid(
+        &mut self,
+        prefix: &str,
+        length: usize,
+        error: ErrorClass,
+    ) -> Result<String, CardGenerationError> {
+        loop {
+            let number = self.generate(prefix, length)?;
+            let mut digits: Vec<u32> = number.chars().map(|c| c.to_digit(10).unwrap()).collect();
+            let free_positions = prefix.len()..length;
+
+            match error {
+                ErrorClass::SingleDigit => {
+                    let position = free_positions.start + self.random_below(free_positions.len());
+                    let offset = 1 + self.random_below(9) as u32;
+                    digits[position] = (digits[position] + offset) % 10;
+                }
+                ErrorClass::AdjacentTransposition => {
+                    if free_positions.len() < 2 {
+                        return Err(CardGenerationError::InvalidLength(length));
+                    }
+                    let position = free_positions.start + self.random_below(free_positions.len() - 1);
+                    digits.swap(position, position + 1);
+                }
+            }
+
+            // Swapping equal digits, or a 0 and a 9, isn't caught by Luhn so try again.
+            if luhn_sum(&digits) % 10 != 0 {
+                return Ok(digits_to_string(&digits));
+            }
+        }
+    }
+}
+
+fn digits_to_string(digits: &[u32]) -> String {
+    digits
+        .iter()
+        .map(|&d| std::char::from_digit(d, 10).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credit::verify_credit_card;
+
+    fn luhn_valid(number: &str) -> bool {
+        let digits: Vec<u32> = number.chars().map(|c| c.to_digit(10).unwrap()).collect();
+        luhn_sum(&digits) % 10 == 0
+    }
+
+    #[test]
+    pub fn generated_numbers_are_valid() {
+        let mut generator = CardGenerator::new(42);
+        let numbers = generator.generate_bulk("411111", 16, 100).unwrap();
+        for number in numbers.iter() {
+            assert_eq!(number.len(), 16);
+            assert!(number.starts_with("411111"));
+            assert!(verify_credit_card(number).is_ok(), "{}", number);
+        }
+    }
+
+    #[test]
+    pub fn generation_is_seeded() {
+        let first = CardGenerator::new(7).generate_bulk("5", 16, 10).unwrap();
+        let second = CardGenerator::new(7).generate_bulk("5", 16, 10).unwrap();
+        let third = CardGenerator::new(8).generate_bulk("5", 16, 10).unwrap();
+        assert_eq!(first, second);
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    pub fn generation_for_scheme() {
+        let mut generator = CardGenerator::new(1);
+        let schemes = [
+            CardScheme::Visa,
+            CardScheme::Mastercard,
+            CardScheme::AmericanExpress,
+            CardScheme::Discover,
+            CardScheme::Jcb,
+            CardScheme::UnionPay,
+            CardScheme::Maestro,
+        ];
+        for &scheme in schemes.iter() {
+            for number in generator.generate_bulk_for_scheme(scheme, 20).unwrap() {
+                assert_eq!(identify_card(&number).scheme, scheme, "{}", number);
+                assert!(verify_credit_card(&number).is_ok(), "{}", number);
+            }
+        }
+        assert_eq!(
+            generator.generate_for_scheme(CardScheme::Unknown),
+            Err(CardGenerationError::UnknownScheme)
+        );
+    }
+
+    #[test]
+    pub fn generation_invalid_arguments() {
+        let mut generator = CardGenerator::new(0);
+        assert_eq!(generator.generate("4a", 16), Err(CardGenerationError::InvalidPrefix));
+        assert_eq!(generator.generate("4111", 4), Err(CardGenerationError::InvalidLength(4)));
+        assert_eq!(generator.generate("4", 20), Err(CardGenerationError::InvalidLength(20)));
+    }
+
+    #[test]
+    pub fn generation_with_errors() {
+        let mut generator = CardGenerator::new(3);
+        for _ in 0..50 {
+            let number = generator.generate_invalid("4", 16, ErrorClass::SingleDigit).unwrap();
+            assert!(number.starts_with('4'));
+            assert!(!luhn_valid(&number), "{}", number);
+
+            let number = generator
+                .generate_invalid("4", 16, ErrorClass::AdjacentTransposition)
+                .unwrap();
+            assert!(!luhn_valid(&number), "{}", number);
+        }
+    }
+}
@@ -0,0 +1,566 @@
+use super::credit::luhn_check_digit;
+use super::hamming::{calculate_hamming_check_digits, HammingError};
+use super::isbn::{isbn10_check_digit, isbn13_check_digit};
+
+use serde::Serialize;
+use std::fmt::Formatter;
+
+const DIGITS: &str = "0123456789";
+const ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum CheckDigitError {
+    InvalidCharacter(char),
+    InvalidLength(usize),
+    CheckDigitMismatch,
+    /// The scheme has no valid check digits for the payload.
+    Unencodable,
+}
+
+impl std::fmt::Display for CheckDigitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckDigitError::InvalidCharacter(character) => {
+                write!(f, "Check digit error: '{}' is not allowed here", character)
+            }
+            CheckDigitError::InvalidLength(length) => {
+                write!(f, "Check digit error: An input of length {} is not supported", length)
+            }
+            CheckDigitError::CheckDigitMismatch => {
+                write!(f, "Check digit error: The check digits do not match the input")
+            }
+            CheckDigitError::Unencodable => {
+                write!(f, "Check digit error: The input has no valid check digits")
+            }
+        }
+    }
+}
+
+/// Kinds of typing mistake a check digit scheme may be guaranteed to catch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum DetectableError {
+    /// One character replaced by another, e.g. 1234 -> 1284.
+    SingleSubstitution,
+    /// Two neighbouring characters swapped, e.g. 1234 -> 1324.
+    AdjacentTransposition,
+    /// Two characters either side of a third swapped, e.g. 1234 -> 3214.
+    JumpTransposition,
+    /// A repeated pair replaced by another, e.g. 1134 -> 2234.
+    TwinError,
+    /// A spoken number misheard, e.g. 13 (thirteen) -> 30 (thirty).
+    PhoneticError,
+}
+
+/// A check digit algorithm, so any identifier can be validated the same way. Spaces and hyphens
+/// are ignored by `compute` and `verify`.
+pub trait CheckDigitScheme {
+    fn name(&self) -> &'static str;
+
+    /// The characters that may appear in an input, check characters included.
+    fn alphabet(&self) -> &'static str;
+
+    /// The number of check characters the scheme appends.
+    fn check_length(&self) -> usize {
+        1
+    }
+
+    /// The error classes the scheme is guaranteed to detect.
+    fn detects(&self) -> &'static [DetectableError];
+
+    /// Computes the check characters for a payload.
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError>;
+
+    /// Verifies an input whose check characters are at the end.
+    fn verify(&self, input: &str) -> Result<(), CheckDigitError> {
+        let input = strip(input);
+        check_alphabet(&input, self.alphabet())?;
+
+        if input.len() <= self.check_length() {
+            return Err(CheckDigitError::InvalidLength(input.len()));
+        }
+
+        let (payload, check) = input.split_at(input.len() - self.check_length());
+        if self.compute(payload)? == check {
+            Ok(())
+        } else {
+            Err(CheckDigitError::CheckDigitMismatch)
+        }
+    }
+}
+
+fn strip(input: &str) -> String {
+    input.chars().filter(|&c| c != ' ' && c != '-').collect()
+}
+
+fn check_alphabet(input: &str, alphabet: &str) -> Result<(), CheckDigitError> {
+    match input.chars().find(|&c| !alphabet.contains(c)) {
+        Some(character) => Err(CheckDigitError::InvalidCharacter(character)),
+        None => Ok(()),
+    }
+}
+
+fn parse_digits(payload: &str) -> Result<Vec<u32>, CheckDigitError> {
+    let payload = strip(payload);
+    check_alphabet(&payload, DIGITS)?;
+    Ok(payload.chars().map(|c| c.to_digit(10).unwrap()).collect())
+}
+
+fn digit_to_string(digit: u32) -> String {
+    std::char::from_digit(digit, 10).unwrap().to_string()
+}
+
+/// The Luhn algorithm used by payment cards, see `credit`.
+pub struct Luhn;
+
+impl CheckDigitScheme for Luhn {
+    fn name(&self) -> &'static str {
+        "Luhn"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        DIGITS
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[DetectableError::SingleSubstitution]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        Ok(digit_to_string(luhn_check_digit(&parse_digits(payload)?)))
+    }
+}
+
+/// ISBN-10, a weighted sum modulo 11 where a check value of 10 is written as 'X', see `isbn`.
+pub struct Isbn10;
+
+impl CheckDigitScheme for Isbn10 {
+    fn name(&self) -> &'static str {
+        "ISBN-10"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        "0123456789X"
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[
+            DetectableError::SingleSubstitution,
+            DetectableError::AdjacentTransposition,
+            DetectableError::JumpTransposition,
+        ]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let payload = strip(payload);
+        check_alphabet(&payload, DIGITS)?;
+        isbn10_check_digit(&payload)
+            .map(String::from)
+            .map_err(|_| CheckDigitError::InvalidLength(payload.len()))
+    }
+}
+
+/// EAN-13, which ISBN-13s are a subset of. Digits are weighted alternately by 1 and 3.
+pub struct Ean13;
+
+impl CheckDigitScheme for Ean13 {
+    fn name(&self) -> &'static str {
+        "EAN-13"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        DIGITS
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[DetectableError::SingleSubstitution]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let payload = strip(payload);
+        check_alphabet(&payload, DIGITS)?;
+        isbn13_check_digit(&payload)
+            .map(String::from)
+            .map_err(|_| CheckDigitError::InvalidLength(payload.len()))
+    }
+}
+
+/// UPC-A, which is an EAN-13 with the leading zero left off.
+pub struct UpcA;
+
+impl CheckDigitScheme for UpcA {
+    fn name(&self) -> &'static str {
+        "UPC-A"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        DIGITS
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[DetectableError::SingleSubstitution]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let payload = strip(payload);
+        check_alphabet(&payload, DIGITS)?;
+        if payload.len() != 11 {
+            return Err(CheckDigitError::InvalidLength(payload.len()));
+        }
+        Ean13.compute(&format!("0{}", payload))
+    }
+}
+
+const VERHOEFF_MULTIPLICATION: [[usize; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+
+const VERHOEFF_PERMUTATION: [[usize; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+];
+
+const VERHOEFF_INVERSE: [usize; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
+
+/// Verhoeff's scheme, built on the dihedral group D5.
+pub struct Verhoeff;
+
+impl CheckDigitScheme for Verhoeff {
+    fn name(&self) -> &'static str {
+        "Verhoeff"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        DIGITS
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[
+            DetectableError::SingleSubstitution,
+            DetectableError::AdjacentTransposition,
+        ]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let check = parse_digits(payload)?
+            .iter()
+            .rev()
+            .enumerate()
+            .fold(0, |check, (i, &digit)| {
+                VERHOEFF_MULTIPLICATION[check][VERHOEFF_PERMUTATION[(i + 1) % 8][digit as usize]]
+            });
+        Ok(digit_to_string(VERHOEFF_INVERSE[check] as u32))
+    }
+}
+
+const DAMM_QUASIGROUP: [[usize; 10]; 10] = [
+    [0, 3, 1, 7, 5, 9, 8, 6, 4, 2],
+    [7, 0, 9, 2, 1, 5, 4, 8, 6, 3],
+    [4, 2, 0, 6, 8, 7, 1, 3, 5, 9],
+    [1, 7, 5, 0, 9, 8, 3, 4, 2, 6],
+    [6, 1, 2, 3, 0, 4, 5, 9, 7, 8],
+    [3, 6, 7, 4, 2, 0, 9, 5, 8, 1],
+    [5, 8, 6, 9, 7, 2, 0, 1, 3, 4],
+    [8, 9, 4, 5, 3, 6, 2, 0, 1, 7],
+    [9, 4, 3, 8, 6, 1, 7, 2, 0, 5],
+    [2, 5, 8, 1, 4, 3, 6, 7, 9, 0],
+];
+
+/// Damm's scheme, built on a totally anti-symmetric quasigroup of order 10.
+pub struct Damm;
+
+impl CheckDigitScheme for Damm {
+    fn name(&self) -> &'static str {
+        "Damm"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        DIGITS
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[
+            DetectableError::SingleSubstitution,
+            DetectableError::AdjacentTransposition,
+        ]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let check = parse_digits(payload)?
+            .iter()
+            .fold(0, |interim, &digit| DAMM_QUASIGROUP[interim][digit as usize]);
+        Ok(digit_to_string(check as u32))
+    }
+}
+
+/// Computes the remainder of a number written in `digits` modulo 97, a few digits at a time so
+/// arbitrarily long inputs don't overflow.
+pub(crate) fn mod97(digits: &str) -> u32 {
+    digits
+        .chars()
+        .fold(0, |remainder, c| (remainder * 10 + c.to_digit(10).unwrap()) % 97)
+}
+
+/// ISO 7064 MOD 97-10, the scheme behind IBAN check digits. Letters are read as the numbers 10
+/// to 35 and the two check digits are appended at the end.
+pub struct Iso7064Mod97;
+
+impl Iso7064Mod97 {
+    pub(crate) fn expand_letters(input: &str) -> String {
+        input
+            .chars()
+            .map(|c| c.to_digit(36).unwrap().to_string())
+            .collect()
+    }
+}
+
+impl CheckDigitScheme for Iso7064Mod97 {
+    fn name(&self) -> &'static str {
+        "ISO 7064 MOD 97-10"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        ALPHANUMERIC
+    }
+
+    fn check_length(&self) -> usize {
+        2
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[
+            DetectableError::SingleSubstitution,
+            DetectableError::AdjacentTransposition,
+            DetectableError::JumpTransposition,
+        ]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let payload = strip(payload);
+        check_alphabet(&payload, ALPHANUMERIC)?;
+        let check = 98 - mod97(&format!("{}00", Self::expand_letters(&payload)));
+        Ok(format!("{:02}", check))
+    }
+
+    fn verify(&self, input: &str) -> Result<(), CheckDigitError> {
+        let input = strip(input);
+        check_alphabet(&input, ALPHANUMERIC)?;
+        if input.len() <= self.check_length() {
+            return Err(CheckDigitError::InvalidLength(input.len()));
+        }
+        if !input[input.len() - 2..].chars().all(|c| c.is_ascii_digit()) {
+            return Err(CheckDigitError::CheckDigitMismatch);
+        }
+
+        if mod97(&Self::expand_letters(&input)) == 1 {
+            Ok(())
+        } else {
+            Err(CheckDigitError::CheckDigitMismatch)
+        }
+    }
+}
+
+/// ISO 7064 MOD 11-2, used by ISNI and ORCID identifiers. A check value of 10 is written as 'X'.
+pub struct Iso7064Mod11;
+
+impl CheckDigitScheme for Iso7064Mod11 {
+    fn name(&self) -> &'static str {
+        "ISO 7064 MOD 11-2"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        "0123456789X"
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[
+            DetectableError::SingleSubstitution,
+            DetectableError::AdjacentTransposition,
+        ]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let remainder = parse_digits(payload)?
+            .iter()
+            .fold(0, |remainder, &digit| (remainder + digit) * 2 % 11);
+        Ok(match (12 - remainder) % 11 {
+            10 => String::from("X"),
+            check => digit_to_string(check),
+        })
+    }
+}
+
+/// The four Hamming check digits appended to six digit inputs, see `hamming`. Payloads that would
+/// need a check digit of 10 can't be encoded.
+pub struct Hamming;
+
+impl CheckDigitScheme for Hamming {
+    fn name(&self) -> &'static str {
+        "Hamming"
+    }
+
+    fn alphabet(&self) -> &'static str {
+        DIGITS
+    }
+
+    fn check_length(&self) -> usize {
+        4
+    }
+
+    fn detects(&self) -> &'static [DetectableError] {
+        &[
+            DetectableError::SingleSubstitution,
+            DetectableError::AdjacentTransposition,
+            DetectableError::JumpTransposition,
+            DetectableError::TwinError,
+            DetectableError::PhoneticError,
+        ]
+    }
+
+    fn compute(&self, payload: &str) -> Result<String, CheckDigitError> {
+        let payload = strip(payload);
+        check_alphabet(&payload, DIGITS)?;
+        calculate_hamming_check_digits(&payload).map_err(|error| match error {
+            HammingError::InvalidLength(_, actual) => CheckDigitError::InvalidLength(actual),
+            _ => CheckDigitError::Unencodable,
+        })
+    }
+}
+
+/// Lists every input that is one substitution from `alphabet` or one swap of adjacent characters
+/// away from `input` and passes `verify`, for offering "did you mean" choices. Characters in
+/// `separators` are kept in place and never substituted or swapped.
+pub fn suggest_corrections<F: Fn(&str) -> bool>(
+    input: &str,
+    alphabet: &str,
+    separators: &[char],
+    verify: F,
+) -> Vec<String> {
+    let characters: Vec<char> = input.chars().collect();
+    let positions: Vec<usize> = (0..characters.len())
+        .filter(|&i| !separators.contains(&characters[i]))
+        .collect();
+
+    let mut candidates: Vec<Vec<char>> = Vec::new();
+    for &position in positions.iter() {
+        for character in alphabet.chars() {
+            let mut candidate = characters.clone();
+            candidate[position] = character;
+            candidates.push(candidate);
+        }
+    }
+    for pair in positions.windows(2) {
+        let mut candidate = characters.clone();
+        candidate.swap(pair[0], pair[1]);
+        candidates.push(candidate);
+    }
+
+    let mut suggestions: Vec<String> = Vec::new();
+    for candidate in candidates {
+        let candidate: String = candidate.into_iter().collect();
+        if candidate != input && !suggestions.contains(&candidate) && verify(&candidate) {
+            suggestions.push(candidate);
+        }
+    }
+    suggestions
+}
+
+/// Every check digit scheme in the crate.
+pub fn schemes() -> Vec<Box<dyn CheckDigitScheme>> {
+    vec![
+        Box::new(Luhn),
+        Box::new(Isbn10),
+        Box::new(Ean13),
+        Box::new(UpcA),
+        Box::new(Verhoeff),
+        Box::new(Damm),
+        Box::new(Iso7064Mod97),
+        Box::new(Iso7064Mod11),
+        Box::new(Hamming),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn corrections_keep_separators() {
+        let luhn = |candidate: &str| Luhn.verify(candidate).is_ok();
+        let suggestions = suggest_corrections("79-927398731", DIGITS, &['-'], luhn);
+        assert!(suggestions.contains(&String::from("79-927398713")));
+        assert!(suggestions
+            .iter()
+            .all(|suggestion| suggestion.find('-') == Some(2) && luhn(suggestion)));
+        assert!(!suggestions.contains(&String::from("79-927398731")));
+    }
+
+    #[test]
+    pub fn check_digit_computation() {
+        let cases: [(&dyn CheckDigitScheme, &str, &str); 11] = [
+            (&Luhn, "7992739871", "3"),
+            (&Isbn10, "0-306-40615", "2"),
+            (&Isbn10, "0-9752298-0", "X"),
+            (&Ean13, "978030640615", "7"),
+            (&UpcA, "03600029145", "2"),
+            (&Verhoeff, "236", "3"),
+            (&Damm, "572", "4"),
+            (&Iso7064Mod97, "794", "44"),
+            (&Iso7064Mod11, "0000-0002-1825-009", "7"),
+            (&Iso7064Mod11, "0000-0002-1694-233", "X"),
+            (&Hamming, "000001", "7671"),
+        ];
+
+        for (scheme, payload, check) in cases.iter() {
+            assert_eq!(scheme.compute(payload), Ok(String::from(*check)), "{}", scheme.name());
+            assert!(scheme.verify(&format!("{}{}", payload, check)).is_ok(), "{}", scheme.name());
+        }
+    }
+
+    #[test]
+    pub fn check_digit_verification_failure() {
+        for scheme in schemes() {
+            let (payload, check) = ["123456789", "12345678901", "123456789012", "000001"]
+                .iter()
+                .find_map(|payload| scheme.compute(payload).ok().map(|check| (payload, check)))
+                .unwrap();
+            let wrong: String = check
+                .chars()
+                .map(|c| if c == '1' { '2' } else { '1' })
+                .collect();
+            assert_eq!(
+                scheme.verify(&format!("{}{}", payload, wrong)),
+                Err(CheckDigitError::CheckDigitMismatch),
+                "{}",
+                scheme.name()
+            );
+            assert_eq!(
+                scheme.verify("12#45"),
+                Err(CheckDigitError::InvalidCharacter('#')),
+                "{}",
+                scheme.name()
+            );
+        }
+    }
+
+    #[test]
+    pub fn iban_check_digits() {
+        // GB82 WEST 1234 5698 7654 32 with the country code and check digits moved to the end
+        assert!(Iso7064Mod97.verify("WEST12345698765432GB82").is_ok());
+        assert_eq!(Iso7064Mod97.compute("WEST12345698765432GB"), Ok(String::from("82")));
+    }
+}
@@ -0,0 +1,374 @@
+//! Birthday attacks on truncated digests. Iterating `x -> first n bits of H(x)` from any start
+//! must eventually loop, and the point where the tail joins the loop is two different inputs with
+//! the same truncated digest. Finding it takes around 2^(n/2) hash evaluations rather than the 2^n
+//! a preimage would.
+
+use super::hash::HashAlgorithm;
+
+use rand_pcg::Pcg64;
+use rand_seeder::rand_core::RngCore;
+use rand_seeder::Seeder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+
+const MAX_BITS: u32 = 64;
+
+/// A distinguished point trail longer than this many times its expected length has most likely
+/// fallen into a loop with no distinguished point on it, so it's abandoned.
+const MAX_TRAIL_FACTOR: u64 = 20;
+
+#[derive(Debug, PartialEq)]
+pub enum CollisionError {
+    InvalidBitCount(u32),
+}
+
+impl fmt::Display for CollisionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Collision {
+    pub first: String,
+    pub second: String,
+    /// The shared first `bits` bits of both digests.
+    pub prefix: u64,
+    pub evaluations: u64,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct CollisionSearch {
+    algorithm: HashAlgorithm,
+    bits: u32,
+    seed: u64,
+}
+
+impl CollisionSearch {
+    /// Searches for collisions in the first `bits` bits of `algorithm`, which must be between 1
+    /// and 64 and no longer than the digest.
+    pub fn new(algorithm: HashAlgorithm, bits: u32) -> Result<Self, CollisionError> {
+        if bits == 0 || bits > MAX_BITS || bits as usize > algorithm.output_len() * 8 {
+            return Err(CollisionError::InvalidBitCount(bits));
+        }
+        Ok(Self {
+            algorithm,
+            bits,
+            seed: 0,
+        })
+    }
+
+    /// Chooses where the walks start, so different seeds find different collisions.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Roughly how many evaluations a birthday attack on this many bits needs, sqrt(pi/2 * 2^n).
+    pub fn expected_evaluations(&self) -> f64 {
+        (std::f64::consts::PI / 2.0 * 2f64.powi(self.bits as i32)).sqrt()
+    }
+
+    fn mask(&self) -> u64 {
+        if self.bits == MAX_BITS {
+            u64::MAX
+        } else {
+            (1 << self.bits) - 1
+        }
+    }
+
+    /// The input an n-bit value stands for: its hex representation, zero padded to a fixed width.
+    fn input(&self, x: u64) -> String {
+        format!("{:0width$x}", x, width = ((self.bits + 3) / 4) as usize)
+    }
+
+    /// The walk function, mapping an n-bit value to the first n bits of the digest of its input.
+    fn step(&self, x: u64) -> u64 {
+        let digest = self.algorithm.digest(self.input(x).as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8.min(digest.len())]);
+        u64::from_be_bytes(bytes) >> (MAX_BITS - self.bits)
+    }
+
+    /// Given two different values that step to the same point, builds the collision.
+    fn collision(&self, a: u64, b: u64, evaluations: u64) -> Collision {
+        Collision {
+            first: self.input(a),
+            second: self.input(b),
+            prefix: self.step(a),
+            evaluations,
+        }
+    }
+
+    /// Walks `lambda` steps behind and ahead in lockstep from `start` until they meet, returning
+    /// the two points just before. `None` if `start` is already on the cycle, in which case there
+    /// is no tail and so no collision.
+    fn find_tail(&self, start: u64, lambda: u64, evaluations: &mut u64) -> Option<(u64, u64)> {
+        let mut ahead = start;
+        for _ in 0..lambda {
+            ahead = self.step(ahead);
+            *evaluations += 1;
+        }
+
+        let mut behind = start;
+        if behind == ahead {
+            return None;
+        }
+        loop {
+            let (next_behind, next_ahead) = (self.step(behind), self.step(ahead));
+            *evaluations += 2;
+            if next_behind == next_ahead {
+                return Some((behind, ahead));
+            }
+            behind = next_behind;
+            ahead = next_ahead;
+        }
+    }
+
+    fn start(&self, attempt: u64) -> u64 {
+        let mut rng: Pcg64 = Seeder::from((self.seed, attempt)).make_rng();
+        rng.next_u64() & self.mask()
+    }
+
+    /// Floyd's tortoise and hare: constant memory, about three evaluations per step.
+    pub fn floyd(&self) -> Collision {
+        let mut evaluations = 0;
+        for attempt in 0.. {
+            let start = self.start(attempt);
+
+            let (mut tortoise, mut hare) = (self.step(start), self.step(self.step(start)));
+            evaluations += 3;
+            while tortoise != hare {
+                tortoise = self.step(tortoise);
+                hare = self.step(self.step(hare));
+                evaluations += 3;
+            }
+
+            // The meeting point is a multiple of the cycle length from the start
+            let mut lambda = 1;
+            let mut walker = self.step(tortoise);
+            evaluations += 1;
+            while walker != tortoise {
+                walker = self.step(walker);
+                lambda += 1;
+                evaluations += 1;
+            }
+
+            if let Some((a, b)) = self.find_tail(start, lambda, &mut evaluations) {
+                return self.collision(a, b, evaluations);
+            }
+        }
+        unreachable!()
+    }
+
+    /// Brent's algorithm: also constant memory, but only one evaluation per step while looking
+    /// for the cycle.
+    pub fn brent(&self) -> Collision {
+        let mut evaluations = 0;
+        for attempt in 0.. {
+            let start = self.start(attempt);
+
+            let (mut power, mut lambda) = (1u64, 1u64);
+            let mut tortoise = start;
+            let mut hare = self.step(start);
+            evaluations += 1;
+            while tortoise != hare {
+                if power == lambda {
+                    tortoise = hare;
+                    power *= 2;
+                    lambda = 0;
+                }
+                hare = self.step(hare);
+                lambda += 1;
+                evaluations += 1;
+            }
+
+            if let Some((a, b)) = self.find_tail(start, lambda, &mut evaluations) {
+                return self.collision(a, b, evaluations);
+            }
+        }
+        unreachable!()
+    }
+
+    /// Parallel collision search with distinguished points (van Oorschot and Wiener). Each thread
+    /// walks from random starts until it reaches a point whose low `distinguished_bits` bits are
+    /// zero, and records where that trail started. Two trails ending at the same point have merged,
+    /// and retracing them gives the collision.
+    pub fn distinguished_points(&self, distinguished_bits: u32) -> Collision {
+        let distinguished_bits = distinguished_bits.min(self.bits - 1);
+        let done = Arc::new(AtomicBool::from(false));
+        let evaluations = Arc::new(AtomicU64::new(0));
+        let trails = Arc::new(Mutex::new(HashMap::new()));
+
+        let handles: Vec<JoinHandle<Option<Collision>>> = (0..num_cpus::get())
+            .map(|thread| {
+                spawn_trail_thread(
+                    *self,
+                    thread as u64,
+                    distinguished_bits,
+                    done.clone(),
+                    evaluations.clone(),
+                    trails.clone(),
+                )
+            })
+            .collect();
+
+        let mut collision = handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .next()
+            .unwrap();
+        collision.evaluations = evaluations.load(Ordering::SeqCst);
+        collision
+    }
+
+    /// Retraces two trails that end at the same distinguished point to where they merge.
+    fn merge_point(&self, (mut a, a_length): (u64, u64), (mut b, b_length): (u64, u64)) -> Option<(u64, u64, u64)> {
+        let mut evaluations = 0;
+        // Line the trails up so both are the same number of steps from the end
+        for _ in b_length..a_length {
+            a = self.step(a);
+            evaluations += 1;
+        }
+        for _ in a_length..b_length {
+            b = self.step(b);
+            evaluations += 1;
+        }
+
+        // One start lay on the other's trail, so they share every point
+        if a == b {
+            return None;
+        }
+        loop {
+            let (next_a, next_b) = (self.step(a), self.step(b));
+            evaluations += 2;
+            if next_a == next_b {
+                return Some((a, b, evaluations));
+            }
+            a = next_a;
+            b = next_b;
+        }
+    }
+}
+
+/// The length at which a trail is abandoned, saturating for distinguished points so rare that no
+/// trail will ever get that long.
+fn max_trail_length(distinguished_bits: u32) -> u64 {
+    MAX_TRAIL_FACTOR.saturating_mul(1 << distinguished_bits)
+}
+
+fn spawn_trail_thread(
+    search: CollisionSearch,
+    thread: u64,
+    distinguished_bits: u32,
+    done: Arc<AtomicBool>,
+    evaluations: Arc<AtomicU64>,
+    trails: Arc<Mutex<HashMap<u64, (u64, u64)>>>,
+) -> JoinHandle<Option<Collision>> {
+    let distinguished_mask = (1u64 << distinguished_bits) - 1;
+    let max_length = max_trail_length(distinguished_bits);
+    let mut rng: Pcg64 = Seeder::from((search.seed, thread)).make_rng();
+
+    thread::spawn(move || {
+        while !done.load(Ordering::SeqCst) {
+            let start = rng.next_u64() & search.mask();
+            // Always take at least one step, so a start that is itself distinguished still makes
+            // a trail that can merge with another
+            let mut point = search.step(start);
+            let mut length = 1;
+            while point & distinguished_mask != 0 && length < max_length {
+                point = search.step(point);
+                length += 1;
+            }
+            evaluations.fetch_add(length, Ordering::SeqCst);
+            if length == max_length {
+                continue;
+            }
+
+            let previous = {
+                let mut trails = trails.lock().unwrap();
+                match trails.get(&point) {
+                    Some(&previous) => Some(previous),
+                    None => {
+                        trails.insert(point, (start, length));
+                        None
+                    }
+                }
+            };
+
+            if let Some(previous) = previous {
+                if let Some((a, b, retrace)) = search.merge_point(previous, (start, length)) {
+                    evaluations.fetch_add(retrace, Ordering::SeqCst);
+                    done.store(true, Ordering::SeqCst);
+                    return Some(search.collision(a, b, 0));
+                }
+            }
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_collides(search: &CollisionSearch, collision: &Collision) {
+        assert_ne!(collision.first, collision.second);
+        let prefix = |input: &str| {
+            let digest = search.algorithm.digest(input.as_bytes());
+            u64::from_be_bytes([
+                digest[0], digest[1], digest[2], digest[3], digest[4], digest[5], digest[6], digest[7],
+            ]) >> (64 - search.bits)
+        };
+        assert_eq!(prefix(&collision.first), collision.prefix);
+        assert_eq!(prefix(&collision.second), collision.prefix);
+        assert!(collision.evaluations > 0);
+    }
+
+    #[test]
+    pub fn cycle_finding_collisions() {
+        for &algorithm in [HashAlgorithm::SHA1, HashAlgorithm::MD5, HashAlgorithm::SHA256].iter() {
+            let search = CollisionSearch::new(algorithm, 24).unwrap().seed(7);
+            let floyd = search.floyd();
+            assert_collides(&search, &floyd);
+            let brent = search.brent();
+            assert_collides(&search, &brent);
+
+            // Both should be within a small factor of the birthday bound
+            assert!((brent.evaluations as f64) < search.expected_evaluations() * 20.0);
+        }
+    }
+
+    #[test]
+    pub fn distinguished_point_collisions() {
+        let search = CollisionSearch::new(HashAlgorithm::SHA256, 28).unwrap().seed(3);
+        let collision = search.distinguished_points(6);
+        assert_collides(&search, &collision);
+    }
+
+    #[test]
+    pub fn bit_count_limits() {
+        assert!(CollisionSearch::new(HashAlgorithm::SHA1, 0).is_err());
+        assert!(CollisionSearch::new(HashAlgorithm::SHA1, 65).is_err());
+        assert!(CollisionSearch::new(HashAlgorithm::SHA1, 64).is_ok());
+
+        let search = CollisionSearch::new(HashAlgorithm::BLAKE3, 8).unwrap();
+        assert_collides(&search, &search.floyd());
+        assert_collides(&search, &search.distinguished_points(0));
+    }
+
+    #[test]
+    pub fn trail_length_saturates() {
+        assert_eq!(max_trail_length(0), MAX_TRAIL_FACTOR);
+        assert_eq!(max_trail_length(6), MAX_TRAIL_FACTOR * 64);
+        assert_eq!(max_trail_length(59), MAX_TRAIL_FACTOR << 59);
+        for bits in 60..MAX_BITS {
+            assert_eq!(max_trail_length(bits), u64::MAX);
+        }
+    }
+}
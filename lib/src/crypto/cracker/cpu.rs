@@ -3,74 +3,157 @@ use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
 
-use sha1::Sha1;
+use super::dictionary;
+use super::handle::CrackHandle;
+use super::job::{Attack, CrackJob, CrackJobBuilder, CrackResult, DEFAULT_ALPHABET};
+use super::mask;
+use super::targets::{Targets, COUNT_INTERVAL};
+use crate::crypto::identify::{self, HashType};
+use crate::crypto::sha1::{self, Sha1};
 
-pub const ALPHABET: [char; 36] = [
-    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w',
-    'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
-];
+pub const ALPHABET: [char; 36] = DEFAULT_ALPHABET;
 
 pub const BCH_ALPHABET: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
 
-pub fn crack_bch(inputs: &[&str]) -> Option<Vec<String>> {
-    let mut results: Vec<String> = Vec::new();
-    for &input in inputs.iter() {
-        if let Some(bch_code) = _crack(&String::from(input), 6, &BCH_ALPHABET, true) {
-            results.push(bch_code);
-        }
+/// Finds the six digit inputs whose BCH codes have the given SHA-1 hashes, with a result for every
+/// input in order.
+pub fn crack_bch(inputs: &[&str]) -> Vec<CrackResult> {
+    _crack(inputs, 6, &BCH_ALPHABET, true)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CrackError {
+    UnrecognisedHash,
+    /// The hash was identified but is salted or uses an algorithm the cracker can't run.
+    UnsupportedHash(HashType),
+}
+
+pub fn crack_single(target: &str) -> Option<String> {
+    let job = CrackJobBuilder::new().target(target).build().ok()?;
+    crack(&job).pop()?.plaintext
+}
+
+/// Runs `job`, returning a result for each target in the order they were given. All the targets
+/// are searched for together, so the keyspace is only enumerated once however many there are.
+pub fn crack(job: &CrackJob) -> Vec<CrackResult> {
+    start(job.clone()).join()
+}
+
+/// Starts `job` on background threads, returning straight away with a handle to follow its
+/// progress, cancel it and wait for its results.
+pub fn start(job: CrackJob) -> CrackHandle {
+    CrackHandle::spawn(job, run)
+}
+
+fn run(job: &CrackJob, targets: &Arc<Targets>, done: &Arc<AtomicBool>) {
+    match job.attack() {
+        Attack::BruteForce => brute_force(job, targets, done, false),
+        Attack::Dictionary { wordlist, rules, .. } => dictionary::run(wordlist, rules, None, targets, done),
+        Attack::Hybrid {
+            wordlist,
+            rules,
+            mask,
+            mode,
+            ..
+        } => dictionary::run(wordlist, rules, Some((mask, *mode)), targets, done),
+        Attack::Mask(mask) => mask::run(mask, targets, done),
     }
+}
 
-    if results.is_empty() {
-        None
-    } else {
-        Some(results)
+/// Identifies the kind of hash `target` is and cracks it with the matching algorithm. Salted and
+/// unrecognised hashes are rejected rather than searched for.
+pub fn crack_hash(target: &str) -> Result<CrackResult, CrackError> {
+    match identify::unsalted_digest(target) {
+        Some((_, algorithm, _)) => {
+            let job = CrackJobBuilder::new()
+                .algorithm(algorithm)
+                .target(target)
+                .build()
+                .map_err(|_| CrackError::UnrecognisedHash)?;
+            Ok(crack(&job).pop().unwrap())
+        }
+        None => match identify::identify(target).first() {
+            Some(&hash_type) => Err(CrackError::UnsupportedHash(hash_type)),
+            None => Err(CrackError::UnrecognisedHash),
+        },
     }
 }
 
-pub fn crack_single(target: &str) -> Option<String> {
-    _crack(target, 6, &ALPHABET, false)
+/// Cracks hex SHA-1 digests in one pass. Targets that aren't valid digests are never searched for
+/// and come back without a plaintext. BCH codes are always exactly `password_length` digits, other
+/// passwords anything up to it.
+pub fn _crack(targets: &[&str], password_length: u32, password_alphabet: &[char], bch: bool) -> Vec<CrackResult> {
+    let valid: Vec<&str> = targets
+        .iter()
+        .cloned()
+        .filter(|target| sha1::from_hex(target).is_some())
+        .collect();
+    let job = match CrackJobBuilder::new()
+        .alphabet(password_alphabet.iter().cloned())
+        .min_length(if bch { password_length as usize } else { 1 })
+        .max_length(password_length as usize)
+        .targets(valid)
+        .build()
+    {
+        Ok(job) => job,
+        Err(_) => return targets.iter().map(|target| CrackResult::new(target)).collect(),
+    };
+
+    let mut cracked = crack_targets(&job, bch).into_iter();
+    targets
+        .iter()
+        .map(|target| match sha1::from_hex(target) {
+            Some(_) => cracked.next().unwrap(),
+            None => CrackResult::new(target),
+        })
+        .collect()
 }
 
-pub fn _crack(target: &str, password_length: u32, password_alphabet: &'static [char], bch: bool) -> Option<String> {
+fn crack_targets(job: &CrackJob, bch: bool) -> Vec<CrackResult> {
+    let targets = Arc::new(Targets::new(job));
+    brute_force(job, &targets, &Arc::new(AtomicBool::from(false)), bch);
+    targets.finish()
+}
+
+/// Runs through the job's keyspace until it's done or `done` is set.
+fn brute_force(job: &CrackJob, targets: &Arc<Targets>, done: &Arc<AtomicBool>, bch: bool) {
     let handles = spawn_worker_threads(
-        target.into(),
-        &password_alphabet,
-        password_length,
-        Arc::new(AtomicBool::from(false)),
+        targets.clone(),
+        Arc::new(job.alphabet().to_vec()),
+        job.min_length(),
+        job.max_length(),
+        done.clone(),
         bch,
     );
 
-    let solution: Vec<String> = handles
-        .into_iter()
-        .map(|h| h.join().unwrap())
-        .filter(|h| h.is_some())
-        .map(|o| o.unwrap())
-        .collect();
-
-    return if solution.is_empty() {
-        None
-    } else {
-        Some(solution.last().unwrap().clone())
-    };
+    for handle in handles {
+        handle.join().unwrap();
+    }
 }
 
-fn create_index_array(min_index: i32, max_length: u32) -> Box<[i32]> {
-    vec![min_index; max_length as usize].into_boxed_slice()
+/// The first candidate: blank (-1) positions for the optional characters, then the shortest
+/// allowed password of the first letter of the alphabet.
+fn create_index_array(min_length: usize, max_length: usize) -> Box<[i32]> {
+    let mut indices = vec![-1; max_length];
+    for index in indices.iter_mut().skip(max_length - min_length) {
+        *index = 0;
+    }
+    indices.into_boxed_slice()
 }
 
+/// Appends the candidate `indices` stand for to `output` as UTF-8, skipping blank positions.
 #[inline]
-fn indices_to_string(indices: &[i32], alphabet: &[char]) -> String {
-    let mut output = String::new();
-    for index in 0..indices.len() {
-        if indices[index] != -1 {
-            output.push(alphabet[indices[index as usize] as usize]);
-        }
+fn push_candidate(indices: &[i32], alphabet: &[char], output: &mut Vec<u8>) {
+    let mut bytes = [0; 4];
+    for &index in indices.iter().filter(|&&index| index != -1) {
+        output.extend_from_slice(alphabet[index as usize].encode_utf8(&mut bytes).as_bytes());
     }
-    output
 }
 
+/// Advances `indices` by `amount` candidates, like an odometer whose position `i` counts up to
+/// `radices[i]`. Blank (-1) positions come in as the count carries into them.
 #[inline]
-fn increment_indices(indices: &mut Box<[i32]>, alphabet_size: usize, amount: i32) -> Result<(), &'static str> {
+pub(super) fn increment_indices(indices: &mut [i32], radices: &[usize], amount: i32) -> Result<(), &'static str> {
     if amount == 0 {
         return Ok(());
     }
@@ -83,12 +166,13 @@ fn increment_indices(indices: &mut Box<[i32]>, alphabet_size: usize, amount: i32
             break;
         }
 
+        let radix = radices[position] as i32;
         let current_value = indices[position];
         let mut new_value = current_value + carry;
 
-        if new_value >= (alphabet_size) as i32 {
-            carry = new_value / alphabet_size as i32;
-            new_value %= alphabet_size as i32;
+        if new_value >= radix {
+            carry = new_value / radix;
+            new_value %= radix;
         } else {
             carry = 0;
         }
@@ -106,85 +190,128 @@ fn increment_indices(indices: &mut Box<[i32]>, alphabet_size: usize, amount: i32
 fn spawn_worker_thread(
     done: Arc<AtomicBool>,
     mut indices: Box<[i32]>,
-    target: Arc<str>,
-    alphabet: Arc<&'static [char]>,
-) -> JoinHandle<Option<String>> {
-    let mut result = None;
-    let mut sha = Sha1::new();
+    targets: Arc<Targets>,
+    alphabet: Arc<Vec<char>>,
+) -> JoinHandle<()> {
     thread::spawn(move || {
+        let radices = vec![alphabet.len(); indices.len()];
+        let last = indices.len() - 1;
+        let sha1 = targets.is_sha1();
+        let mut tried = 0;
+
+        // Candidates are built in one buffer. Consecutive ones mostly differ only in the last
+        // position, so everything before it is only rebuilt, and for SHA-1 hashed, when it changes.
+        let mut candidate = Vec::new();
+        let mut prefix_indices = vec![-2; last];
+        let mut prefix_len = 0;
+        let mut prefix = Sha1::new();
         loop {
-            if increment_indices(&mut indices, alphabet.len(), num_cpus::get() as i32).is_err()
-                || done.load(Ordering::SeqCst)
-            {
-                break;
+            if indices[..last] != prefix_indices[..] {
+                prefix_indices.copy_from_slice(&indices[..last]);
+                candidate.clear();
+                push_candidate(&indices[..last], &alphabet, &mut candidate);
+                prefix_len = candidate.len();
+                if sha1 {
+                    prefix = Sha1::new();
+                    prefix.update(&candidate);
+                }
             }
+            candidate.truncate(prefix_len);
+            push_candidate(&indices[last..], &alphabet, &mut candidate);
 
-            sha.update(&indices_to_string(&indices, &alphabet).as_bytes());
-            let hashed_password = sha.digest().to_string();
-            if target == Arc::from(hashed_password) {
-                done.store(true, Ordering::SeqCst);
-                result = Some(indices_to_string(&indices, &alphabet));
+            tried += 1;
+            let positions = if sha1 {
+                let mut hasher = prefix.clone();
+                hasher.update(&candidate[prefix_len..]);
+                targets.lookup_sha1(&hasher.finish())
+            } else {
+                targets.lookup(&candidate)
+            };
+            if let Some(positions) = positions {
+                // Made of whole characters, so always UTF-8
+                if targets.found(positions, std::str::from_utf8(&candidate).unwrap(), tried) {
+                    done.store(true, Ordering::SeqCst);
+                }
+            }
+
+            if tried == COUNT_INTERVAL {
+                targets.count(tried);
+                tried = 0;
+            }
+            if increment_indices(&mut indices, &radices, num_cpus::get() as i32).is_err() || done.load(Ordering::SeqCst)
+            {
+                break;
             }
-            sha.reset();
         }
-        result
+        targets.count(tried);
     })
 }
 
 fn spawn_worker_thread_for_bch(
     done: Arc<AtomicBool>,
     mut indices: Box<[i32]>,
-    target: Arc<str>,
-    alphabet: Arc<&'static [char]>,
-) -> JoinHandle<Option<String>> {
-    let mut result = None;
-    let mut sha = Sha1::new();
+    targets: Arc<Targets>,
+    alphabet: Arc<Vec<char>>,
+) -> JoinHandle<()> {
     thread::spawn(move || {
+        let radices = vec![alphabet.len(); indices.len()];
+        let mut tried = 0;
+        let mut candidate = Vec::new();
         loop {
-            if let Ok(bch) = crate::bch::encode_bch(&indices_to_string(&indices, &alphabet)) {
-                sha.update(&bch.as_bytes());
-                let hashed_password = sha.digest().to_string();
-                if target == Arc::from(hashed_password) {
-                    done.store(true, Ordering::SeqCst);
-                    result = Some(indices_to_string(&indices, &alphabet));
+            candidate.clear();
+            push_candidate(&indices, &alphabet, &mut candidate);
+            // Made of whole characters, so always UTF-8
+            let code = std::str::from_utf8(&candidate).unwrap();
+            tried += 1;
+            if let Ok(bch) = crate::bch::encode_bch(code) {
+                if let Some(positions) = targets.lookup(bch.as_bytes()) {
+                    if targets.found(positions, code, tried) {
+                        done.store(true, Ordering::SeqCst);
+                    }
                 }
             }
-            sha.reset();
 
-            if increment_indices(&mut indices, alphabet.len(), num_cpus::get() as i32).is_err()
-                || done.load(Ordering::SeqCst)
+            if tried == COUNT_INTERVAL {
+                targets.count(tried);
+                tried = 0;
+            }
+            if increment_indices(&mut indices, &radices, num_cpus::get() as i32).is_err() || done.load(Ordering::SeqCst)
             {
                 break;
             }
         }
-        result
+        targets.count(tried);
     })
 }
 
 fn spawn_worker_threads(
-    target: Arc<str>,
-    alphabet: &'static [char],
-    password_length: u32,
+    targets: Arc<Targets>,
+    alphabet: Arc<Vec<char>>,
+    min_length: usize,
+    max_length: usize,
     done: Arc<AtomicBool>,
     bch: bool,
-) -> Vec<JoinHandle<Option<String>>> {
+) -> Vec<JoinHandle<()>> {
     let mut handles = vec![];
     for thread in 0..num_cpus::get() {
-        let mut indices = create_index_array(if bch { 0 } else { -1 }, password_length);
-        increment_indices(&mut indices, alphabet.len(), thread as i32).unwrap();
+        let mut indices = create_index_array(min_length, max_length);
+        // Keyspaces smaller than the thread count leave the extra threads with nothing to do
+        if increment_indices(&mut indices, &vec![alphabet.len(); max_length], thread as i32).is_err() {
+            break;
+        }
         if !bch {
             handles.push(spawn_worker_thread(
                 done.clone(),
                 indices,
-                target.clone(),
-                Arc::new(&alphabet),
+                targets.clone(),
+                alphabet.clone(),
             ));
         } else {
             handles.push(spawn_worker_thread_for_bch(
                 done.clone(),
                 indices,
-                target.clone(),
-                Arc::new(&alphabet),
+                targets.clone(),
+                alphabet.clone(),
             ));
         }
     }
@@ -194,8 +321,13 @@ fn spawn_worker_threads(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::hash::HashAlgorithm;
     use std::time::Instant;
 
+    fn plaintexts(results: Vec<CrackResult>) -> Vec<Option<String>> {
+        results.into_iter().map(|result| result.plaintext).collect()
+    }
+
     #[test]
     pub fn simple_crack() {
         let hash = "c2543fff3bfa6f144c2f06a7de6cd10c0b650cae";
@@ -203,6 +335,135 @@ mod tests {
         assert_eq!(password, "this");
     }
 
+    #[test]
+    pub fn identified_crack() {
+        let md5 = HashAlgorithm::MD5.hex_digest(b"dog");
+        assert_eq!(
+            crack_hash(&md5).map(|result| result.plaintext),
+            Ok(Some(String::from("dog")))
+        );
+        assert_eq!(
+            crack_hash("{SHA}nZiejSfcng7DOJ/IVfFCw9QPDFA=").map(|result| result.plaintext),
+            Ok(Some(String::from("cat")))
+        );
+        assert_eq!(
+            crack_hash("$2b$12$GhvMmNVjRW29ulnudl.LbuAnUtN/LRfe1JsBm1Xu6LE3059z5Tr8m"),
+            Err(CrackError::UnsupportedHash(HashType::BCrypt))
+        );
+        assert_eq!(crack_hash("not a hash"), Err(CrackError::UnrecognisedHash));
+    }
+
+    #[test]
+    pub fn configured_jobs() {
+        let targets = [
+            HashAlgorithm::SHA256.hex_digest("Zé!".as_bytes()),
+            HashAlgorithm::SHA256.hex_digest(b"!!!!"),
+            HashAlgorithm::SHA256.hex_digest(b"a"),
+        ];
+        let job = CrackJobBuilder::new()
+            .algorithm(HashAlgorithm::SHA256)
+            .alphabet("!Zé".chars())
+            .min_length(3)
+            .max_length(4)
+            .targets(targets.iter().map(String::as_str))
+            .build()
+            .unwrap();
+        assert_eq!(
+            plaintexts(crack(&job)),
+            vec![Some(String::from("Zé!")), Some(String::from("!!!!")), None]
+        );
+
+        // The very first candidate of a keyspace smaller than the thread count
+        let job = CrackJobBuilder::new()
+            .alphabet("xy".chars())
+            .length(1)
+            .target(&HashAlgorithm::SHA1.hex_digest(b"x"))
+            .build()
+            .unwrap();
+        assert_eq!(plaintexts(crack(&job)), vec![Some(String::from("x"))]);
+    }
+
+    #[test]
+    pub fn multiple_targets_in_one_pass() {
+        let hash = |password: &str| HashAlgorithm::SHA1.hex_digest(password.as_bytes());
+        let job = CrackJobBuilder::new()
+            .alphabet("abc1".chars())
+            .max_length(4)
+            .targets(vec![
+                hash("1111").as_str(),
+                hash("a").as_str(),
+                hash("not in the keyspace").as_str(),
+                hash("cab").as_str(),
+                hash("a").as_str(),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            plaintexts(crack(&job)),
+            vec![
+                Some(String::from("1111")),
+                Some(String::from("a")),
+                None,
+                Some(String::from("cab")),
+                Some(String::from("a")),
+            ]
+        );
+
+        // Invalid digests don't stop the others being cracked
+        assert_eq!(
+            plaintexts(_crack(
+                &[hash("bb").as_str(), "zz", hash("c1").as_str()],
+                2,
+                &['b', 'c', '1'],
+                false
+            )),
+            vec![Some(String::from("bb")), None, Some(String::from("c1"))]
+        );
+    }
+
+    #[test]
+    pub fn long_candidates() {
+        // Longer than a SHA-1 block, so the cached state for the start of each candidate has
+        // already compressed a block; the second target needs that state rebuilt.
+        let first = format!("{}é", "x".repeat(69));
+        let second = format!("{}éx", "x".repeat(68));
+        let job = CrackJobBuilder::new()
+            .alphabet("xé".chars())
+            .length(70)
+            .targets(vec![
+                HashAlgorithm::SHA1.hex_digest(second.as_bytes()).as_str(),
+                HashAlgorithm::SHA1.hex_digest(first.as_bytes()).as_str(),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(plaintexts(crack(&job)), vec![Some(second), Some(first)]);
+    }
+
+    #[test]
+    pub fn result_statistics() {
+        let missing = HashAlgorithm::SHA1.hex_digest(b"missing");
+        let found = HashAlgorithm::SHA1.hex_digest(b"dcba");
+        let job = CrackJobBuilder::new()
+            .alphabet("abcd".chars())
+            .max_length(5)
+            .targets(vec![missing.as_str(), found.as_str()])
+            .build()
+            .unwrap();
+        let results = crack(&job);
+
+        // A target that isn't there means the whole keyspace was searched
+        assert_eq!(results[0].hash, missing);
+        assert_eq!(results[0].plaintext, None);
+        assert_eq!(results[0].candidates_tried, job.keyspace());
+
+        assert_eq!(results[1].hash, found);
+        assert!(results[1].candidates_tried > 0 && results[1].candidates_tried <= job.keyspace());
+        assert!(results[1].elapsed <= results[0].elapsed);
+
+        let json = serde_json::to_string(&results[1]).unwrap();
+        assert_eq!(serde_json::from_str::<CrackResult>(&json).unwrap(), results[1]);
+    }
+
     #[test]
     pub fn crack_late_permutation() {
         let hash = "1f5523a8f535289b3401b29958d01b2966ed61d2";
@@ -222,9 +483,16 @@ mod tests {
             "902608824fae2a1918d54d569d20819a4288a4e4",
             "5b8f495b7f02b62eb228c5dbece7c2f81b60b9a3",
         ];
-        let codes = crack_bch(&hashes).unwrap();
-        assert_eq!(codes[0], "000001");
-        assert_eq!(codes[1], "000011");
-        assert_eq!(codes[2], "888888");
+        let codes = crack_bch(&hashes);
+        assert_eq!(codes[0].plaintext.as_deref(), Some("000001"));
+        assert_eq!(codes[1].plaintext.as_deref(), Some("000011"));
+        assert_eq!(codes[2].plaintext.as_deref(), Some("888888"));
+
+        // Unmatched hashes keep their place rather than being dropped
+        let codes = crack_bch(&["aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d", hashes[0]]);
+        assert_eq!(codes[0].hash, "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d");
+        assert_eq!(codes[0].plaintext, None);
+        assert_eq!(codes[0].candidates_tried, 1_000_000);
+        assert_eq!(codes[1].plaintext.as_deref(), Some("000001"));
     }
 }
@@ -0,0 +1,424 @@
+//! Wordlist attacks: every word of a wordlist put through every rule, checked against all of a
+//! job's targets at once. The wordlist is memory mapped rather than read in, so it can be far
+//! larger than memory, and each thread works through its own slice of it. Hybrid attacks also
+//! combine each mangled word with every candidate of a mask.
+
+use super::job::HybridMode;
+use super::mask::Mask;
+use super::rules::Rule;
+use super::targets::{Targets, COUNT_INTERVAL};
+
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+
+/// A wordlist file, one word per line. Empty lines are skipped and Windows line endings are
+/// accepted. Words are bytes, so wordlists in any encoding work; a plaintext that isn't UTF-8 is
+/// reported in hashcat's `$HEX[...]` form.
+pub struct Wordlist {
+    /// `None` for an empty file, which can't be mapped.
+    map: Option<Mmap>,
+}
+
+impl Wordlist {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(Self { map: None });
+        }
+        // The map is only read, but changes made to the file while it's open will show through
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self { map: Some(map) })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &[u8]> {
+        words(self.bytes())
+    }
+
+    /// The number of words, the same as `words().count()` but in a single `memchr` pass over the
+    /// file.
+    pub fn word_count(&self) -> u64 {
+        let bytes = self.bytes();
+        let mut count = 0;
+        let mut start = 0;
+        for end in memchr::memchr_iter(b'\n', bytes).chain(std::iter::once(bytes.len())) {
+            let line = &bytes[start..end];
+            if !line.is_empty() && line != b"\r" {
+                count += 1;
+            }
+            start = end + 1;
+        }
+        count
+    }
+
+    /// Splits the file into at most `parts` byte ranges of about the same size, each ending at
+    /// the end of a line.
+    pub fn split(&self, parts: usize) -> Vec<Range<usize>> {
+        let bytes = self.bytes();
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for part in 1..=parts {
+            if start >= bytes.len() {
+                break;
+            }
+            let mut end = (bytes.len() * part / parts).max(start);
+            end = match memchr::memchr(b'\n', &bytes[end..]) {
+                Some(newline) => end + newline + 1,
+                None => bytes.len(),
+            };
+            ranges.push(start..end);
+            start = end;
+        }
+        ranges
+    }
+}
+
+pub(super) fn words(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    bytes
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|word| !word.is_empty())
+}
+
+/// Runs a dictionary attack, or a hybrid one if there's a mask, until it's been through the
+/// wordlist or `done` is set. Every rule, and every candidate of the mask, is tried with a word
+/// before moving on to the next one.
+pub(super) fn run(
+    wordlist: &Path,
+    rules: &[Rule],
+    mask: Option<(&Mask, HybridMode)>,
+    targets: &Arc<Targets>,
+    done: &Arc<AtomicBool>,
+) {
+    let wordlist = match Wordlist::open(wordlist) {
+        Ok(wordlist) => Arc::new(wordlist),
+        Err(_) => return,
+    };
+    let rules = Arc::new(rules.to_vec());
+    let mask = Arc::new(mask.map(|(mask, mode)| (mask.clone(), mode)));
+
+    let handles: Vec<JoinHandle<()>> = wordlist
+        .split(num_cpus::get())
+        .into_iter()
+        .map(|range| {
+            spawn_worker_thread(
+                done.clone(),
+                range,
+                wordlist.clone(),
+                rules.clone(),
+                mask.clone(),
+                targets.clone(),
+            )
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+fn spawn_worker_thread(
+    done: Arc<AtomicBool>,
+    range: Range<usize>,
+    wordlist: Arc<Wordlist>,
+    rules: Arc<Vec<Rule>>,
+    mask: Arc<Option<(Mask, HybridMode)>>,
+    targets: Arc<Targets>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut tried = 0;
+        let mut candidate = Vec::new();
+        'words: for word in words(&wordlist.bytes()[range]) {
+            for rule in rules.iter() {
+                let mangled = match rule.apply(word) {
+                    Some(mangled) => mangled,
+                    // Rejected candidates are still part of the keyspace, so they count as tried
+                    None => {
+                        let rejected = match mask.as_ref() {
+                            Some((mask, _)) => mask.keyspace(),
+                            None => 1,
+                        };
+                        skip(&targets, rejected, &mut tried);
+                        continue;
+                    }
+                };
+                match mask.as_ref() {
+                    None => {
+                        if check(&targets, &mangled, &mut tried) {
+                            done.store(true, Ordering::SeqCst);
+                        }
+                    }
+                    Some((mask, mode)) => {
+                        for masked in mask.candidates() {
+                            candidate.clear();
+                            match mode {
+                                HybridMode::WordlistMask => {
+                                    candidate.extend_from_slice(&mangled);
+                                    candidate.extend_from_slice(masked.as_bytes());
+                                }
+                                HybridMode::MaskWordlist => {
+                                    candidate.extend_from_slice(masked.as_bytes());
+                                    candidate.extend_from_slice(&mangled);
+                                }
+                            }
+                            if check(&targets, &candidate, &mut tried) {
+                                done.store(true, Ordering::SeqCst);
+                            }
+                            // Masks can be big enough that waiting for the next word would hold up
+                            // a cancelled job
+                            if done.load(Ordering::SeqCst) {
+                                break 'words;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if done.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+        targets.count(tried);
+    })
+}
+
+/// Tries one candidate against the targets, returning whether every target has now been found.
+/// `tried` is the worker's local count.
+#[inline]
+fn check(targets: &Targets, candidate: &[u8], tried: &mut u64) -> bool {
+    *tried += 1;
+    let finished = match targets.lookup(candidate) {
+        Some(positions) => targets.found(positions, &plaintext(candidate), *tried),
+        None => false,
+    };
+
+    if *tried >= COUNT_INTERVAL {
+        targets.count(*tried);
+        *tried = 0;
+    }
+    finished
+}
+
+/// The candidate as a plaintext to report. Anything that isn't UTF-8 is written as `$HEX[...]`
+/// with its bytes in hex, as hashcat does, so the exact bytes can be recovered. So is a UTF-8
+/// candidate that already starts `$HEX[`, which would be ambiguous otherwise.
+fn plaintext(candidate: &[u8]) -> String {
+    match std::str::from_utf8(candidate) {
+        Ok(word) if !word.starts_with("$HEX[") => String::from(word),
+        _ => format!("$HEX[{}]", hex::encode(candidate)),
+    }
+}
+
+/// Counts `rejected` candidates as tried without hashing them.
+fn skip(targets: &Targets, rejected: u64, tried: &mut u64) {
+    *tried = tried.saturating_add(rejected);
+    if *tried >= COUNT_INTERVAL {
+        targets.count(*tried);
+        *tried = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::cracker::cpu;
+    use crate::crypto::cracker::job::{CrackJobBuilder, CrackJobError};
+    use crate::crypto::cracker::rules::default_rules;
+    use crate::crypto::hash::HashAlgorithm;
+
+    #[test]
+    pub fn cracks_mangled_words() {
+        let path = std::env::temp_dir().join(format!("crypto-wordlist-crack-{}", std::process::id()));
+        std::fs::write(&path, "password\nmonkey\r\n\ndragon\nletmein\n").unwrap();
+
+        let hashes: Vec<String> = ["Monkey123", "p@ssw0rd", "nogard", "not in the list", "letmein"]
+            .iter()
+            .map(|word| HashAlgorithm::SHA256.hex_digest(word.as_bytes()))
+            .collect();
+        let job = CrackJobBuilder::new()
+            .algorithm(HashAlgorithm::SHA256)
+            .wordlist(&path)
+            .rules(default_rules())
+            .targets(hashes.iter().map(String::as_str))
+            .build()
+            .unwrap();
+        let results = cpu::crack(&job);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(job.keyspace(), 4 * default_rules().len() as u64);
+        let plaintexts: Vec<Option<&str>> = results.iter().map(|result| result.plaintext.as_deref()).collect();
+        assert_eq!(
+            plaintexts,
+            vec![
+                Some("Monkey123"),
+                Some("p@ssw0rd"),
+                Some("nogard"),
+                None,
+                Some("letmein")
+            ]
+        );
+        assert_eq!(results[3].candidates_tried, job.keyspace());
+
+        assert_eq!(
+            CrackJobBuilder::new()
+                .algorithm(HashAlgorithm::SHA256)
+                .wordlist(&path)
+                .target(&hashes[0])
+                .build(),
+            Err(CrackJobError::UnreadableWordlist(path))
+        );
+    }
+
+    #[test]
+    pub fn counts_rejected_candidates() {
+        let path = std::env::temp_dir().join(format!("crypto-wordlist-rejected-{}", std::process::id()));
+        let long = "x".repeat(300);
+        std::fs::write(&path, format!("abc\n{}\nxyz\n", long)).unwrap();
+
+        let hashes = [
+            HashAlgorithm::SHA1.hex_digest(&b"abc".repeat(64)),
+            HashAlgorithm::SHA1.hex_digest(b"missing"),
+        ];
+        let dictionary = CrackJobBuilder::new()
+            .wordlist(&path)
+            .rules(vec![
+                Rule::identity(),
+                "dddddd".parse().unwrap(),
+                "dddddddddd".parse().unwrap(),
+            ])
+            .targets(hashes.iter().map(String::as_str))
+            .build()
+            .unwrap();
+        let hybrid = CrackJobBuilder::new()
+            .wordlist(&path)
+            .rules(vec!["dddddd".parse().unwrap()])
+            .mask("?d".parse().unwrap())
+            .targets(hashes.iter().map(String::as_str))
+            .build()
+            .unwrap();
+        let results = [cpu::crack(&dictionary), cpu::crack(&hybrid)];
+        std::fs::remove_file(&path).unwrap();
+
+        // The 300 byte word and the rules taking words past 256 bytes are skipped, but the
+        // keyspace is still accounted for
+        assert_eq!(results[0][0].plaintext, Some("abc".repeat(64)));
+        assert_eq!(results[0][1].candidates_tried, dictionary.keyspace());
+        assert_eq!(results[1][1].candidates_tried, hybrid.keyspace());
+        assert_eq!(hybrid.keyspace(), 3 * 10);
+    }
+
+    #[test]
+    pub fn cracks_hybrid_candidates() {
+        let path = std::env::temp_dir().join(format!("crypto-wordlist-hybrid-{}", std::process::id()));
+        std::fs::write(&path, "password\ndragon\nsunshine\n").unwrap();
+
+        let hash = |password: &str| HashAlgorithm::SHA1.hex_digest(password.as_bytes());
+        let hashes = [hash("dragon07"), hash("Password!9"), hash("07dragon"), hash("sunshine")];
+        let job = |mode| {
+            CrackJobBuilder::new()
+                .wordlist(&path)
+                .rules(vec![Rule::identity(), "c".parse().unwrap()])
+                .mask("-1 !0 ?1?d".parse().unwrap())
+                .hybrid_mode(mode)
+                .targets(hashes.iter().map(String::as_str))
+                .build()
+                .unwrap()
+        };
+        let append = job(HybridMode::WordlistMask);
+        let prepend = job(HybridMode::MaskWordlist);
+        let appended = cpu::crack(&append);
+        let prepended = cpu::crack(&prepend);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(append.keyspace(), 3 * 2 * 20);
+        let plaintexts: Vec<Option<&str>> = appended.iter().map(|result| result.plaintext.as_deref()).collect();
+        assert_eq!(plaintexts, vec![Some("dragon07"), Some("Password!9"), None, None]);
+        assert_eq!(appended[3].candidates_tried, append.keyspace());
+
+        let plaintexts: Vec<Option<&str>> = prepended.iter().map(|result| result.plaintext.as_deref()).collect();
+        assert_eq!(plaintexts, vec![None, None, Some("07dragon"), None]);
+    }
+
+    #[test]
+    pub fn splits_on_line_boundaries() {
+        let path = std::env::temp_dir().join(format!("crypto-wordlist-split-{}", std::process::id()));
+        let text: String = (0..1000).map(|i| format!("word{}\r\n", i)).collect();
+        std::fs::write(&path, &text).unwrap();
+        let wordlist = Wordlist::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for &parts in [1, 3, 7, 64, 5000].iter() {
+            let ranges = wordlist.split(parts);
+            assert!(ranges.len() <= parts);
+            let words: Vec<&[u8]> = ranges
+                .iter()
+                .flat_map(|range| words(&wordlist.bytes()[range.clone()]))
+                .collect();
+            assert_eq!(words.len(), 1000, "{}", parts);
+            assert_eq!(words[999], b"word999");
+        }
+        assert_eq!(wordlist.word_count(), 1000);
+
+        let blanks = std::env::temp_dir().join(format!("crypto-wordlist-blanks-{}", std::process::id()));
+        std::fs::write(&blanks, "\n\r\na\n\n\r\r\nb\r\nc").unwrap();
+        let wordlist = Wordlist::open(&blanks).unwrap();
+        std::fs::remove_file(&blanks).unwrap();
+        assert_eq!(wordlist.word_count(), wordlist.words().count() as u64);
+        assert_eq!(wordlist.word_count(), 4);
+
+        let empty = std::env::temp_dir().join(format!("crypto-wordlist-empty-{}", std::process::id()));
+        std::fs::write(&empty, "").unwrap();
+        let wordlist = Wordlist::open(&empty).unwrap();
+        std::fs::remove_file(&empty).unwrap();
+        assert_eq!(wordlist.words().count(), 0);
+        assert_eq!(wordlist.word_count(), 0);
+        assert!(wordlist.split(4).is_empty());
+    }
+
+    #[test]
+    pub fn reports_non_utf8_words_in_hex() {
+        let path = std::env::temp_dir().join(format!("crypto-wordlist-latin1-{}", std::process::id()));
+        // "café" and "naïve" in Latin-1, which aren't valid UTF-8
+        std::fs::write(&path, b"caf\xe9\nna\xefve\n$HEX[41]\n").unwrap();
+
+        let hashes = [
+            HashAlgorithm::SHA256.hex_digest(b"caf\xe9"),
+            HashAlgorithm::SHA256.hex_digest(b"NA\xefVE"),
+            HashAlgorithm::SHA256.hex_digest(b"$HEX[41]"),
+        ];
+        let job = CrackJobBuilder::new()
+            .algorithm(HashAlgorithm::SHA256)
+            .wordlist(&path)
+            .rules(vec![Rule::identity(), "u".parse().unwrap()])
+            .targets(hashes.iter().map(String::as_str))
+            .build()
+            .unwrap();
+        let results = cpu::crack(&job);
+        std::fs::remove_file(&path).unwrap();
+
+        let plaintexts: Vec<Option<&str>> = results.iter().map(|result| result.plaintext.as_deref()).collect();
+        assert_eq!(
+            plaintexts,
+            vec![
+                Some("$HEX[636166e9]"),
+                Some("$HEX[4e41ef5645]"),
+                Some("$HEX[244845585b34315d]")
+            ]
+        );
+        for (result, hash) in results.iter().zip(hashes.iter()) {
+            let hex = result.plaintext.as_deref().unwrap();
+            let bytes = hex::decode(&hex[5..hex.len() - 1]).unwrap();
+            assert_eq!(HashAlgorithm::SHA256.hex_digest(&bytes), *hash);
+        }
+    }
+}
@@ -1,36 +1,72 @@
+use super::job::{Attack, CrackJob, CrackJobBuilder, CrackResult};
+use crate::crypto::hash::HashAlgorithm;
 use ocl::{Buffer, MemFlags, ProQue};
+use std::time::Instant;
+
+/// Bytes the kernel sets aside for each alphabet character, enough for any UTF-8 encoding.
+const CHAR_WIDTH: usize = 4;
+
+/// Candidates per kernel launch. Bigger keyspaces are split into launches of this many, each
+/// starting from the previous one's end, so the global work size stays within what devices accept.
+const BATCH_SIZE: u64 = 1 << 24;
 
 #[allow(dead_code)]
-pub fn crack(inputs: &[&str]) -> Option<Vec<String>> {
-    let src = include_str!("kernel.cl");
+pub fn crack(inputs: &[&str]) -> Option<Vec<CrackResult>> {
+    // The kernel only implements SHA-1, so any other kind of hash fails the job and rejects the
+    // whole batch
+    let job = CrackJobBuilder::new().targets(inputs.iter().cloned()).build().ok()?;
+    crack_job(&job)
+}
+
+/// Runs `job` on the GPU, returning a result for each target in order. Only SHA-1 brute force jobs
+/// with a keyspace the kernel's 64 bit counter can index are supported. The keyspace is searched
+/// in batches until every target is found, and a found target's time and candidates tried are
+/// counted to the end of the batch it was found in.
+pub fn crack_job(job: &CrackJob) -> Option<Vec<CrackResult>> {
+    if job.algorithm() != HashAlgorithm::SHA1 || *job.attack() != Attack::BruteForce || job.keyspace() > i64::MAX as u64
+    {
+        return None;
+    }
+    let start = Instant::now();
 
-    let pro_que = ProQue::builder().src(src).dims(4000000000_usize).build().ok()?;
+    let src = format!(
+        "#define ALPHABET_SIZE {}\n#define PASSWORD_SIZE {}\n#define MIN_PASSWORD_SIZE {}\n{}",
+        job.alphabet().len(),
+        job.max_length(),
+        job.min_length(),
+        include_str!("kernel.cl")
+    );
 
-    let raw_ascii_alphabet: Vec<u8> = vec![
-        'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v',
-        'w', 'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
-    ]
-    .iter()
-    .map(|elem| *elem as u8)
-    .collect();
+    let pro_que = ProQue::builder()
+        .src(&src)
+        .dims(BATCH_SIZE.min(job.keyspace()) as usize)
+        .build()
+        .ok()?;
 
-    let hexes: Vec<Vec<u8>> = inputs
+    let mut raw_alphabet = vec![0u8; job.alphabet().len() * CHAR_WIDTH];
+    let alphabet_lengths: Vec<u8> = job
+        .alphabet()
         .iter()
-        .map(|input| {
-            (0..input.len())
-                .step_by(2)
-                .map(|i| u8::from_str_radix(&input[i..i + 2], 16).unwrap())
-                .collect()
-        })
+        .zip(raw_alphabet.chunks_exact_mut(CHAR_WIDTH))
+        .map(|(c, bytes)| c.encode_utf8(bytes).len() as u8)
         .collect();
 
-    let targets: Vec<u8> = hexes.iter().flatten().cloned().collect();
+    let targets: Vec<u8> = job.digests().concat();
+    let output_size = job.max_length() * CHAR_WIDTH;
 
     let alphabet = Buffer::builder()
         .queue(pro_que.queue().clone())
         .flags(MemFlags::new().read_only())
-        .len(raw_ascii_alphabet.len())
-        .copy_host_slice(&raw_ascii_alphabet)
+        .len(raw_alphabet.len())
+        .copy_host_slice(&raw_alphabet)
+        .build()
+        .ok()?;
+
+    let lengths = Buffer::builder()
+        .queue(pro_que.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(alphabet_lengths.len())
+        .copy_host_slice(&alphabet_lengths)
         .build()
         .ok()?;
 
@@ -45,20 +81,21 @@ pub fn crack(inputs: &[&str]) -> Option<Vec<String>> {
     let done = Buffer::builder()
         .queue(pro_que.queue().clone())
         .len(1)
-        .copy_host_slice(&[0])
+        .copy_host_slice(&[0u32])
         .build()
         .ok()?;
 
     let num_targets = Buffer::builder()
         .queue(pro_que.queue().clone())
         .len(1)
-        .copy_host_slice(&[targets.len() / 20])
+        .copy_host_slice(&[job.digests().len() as u32])
         .build()
         .ok()?;
 
     let outputs = Buffer::builder()
         .queue(pro_que.queue().clone())
-        .len(6 * targets.len())
+        .len(output_size * job.digests().len())
+        .copy_host_slice(&vec![0u8; output_size * job.digests().len()])
         .build()
         .ok()?;
 
@@ -66,31 +103,57 @@ pub fn crack(inputs: &[&str]) -> Option<Vec<String>> {
         .kernel_builder("add")
         .arg(&outputs)
         .arg(&alphabet)
+        .arg(&lengths)
         .arg(&target)
         .arg(&done)
         .arg(&num_targets)
+        .arg_named("base", &0u64)
         .build()
         .ok()?;
 
-    unsafe {
-        kernel.enq().ok()?;
-    }
-
+    let mut tried = 0;
+    let mut found = vec![0u32];
     let mut vec = vec![0u8; outputs.len()];
-    outputs.read(&mut vec).enq().ok()?;
-
-    let mut results = Vec::new();
+    // The run time and candidates tried when each target was found, to the nearest batch
+    let mut found_at = vec![None; job.digests().len()];
+    while tried < job.keyspace() && (found[0] as usize) < job.digests().len() {
+        let batch = BATCH_SIZE.min(job.keyspace() - tried);
+        kernel.set_arg("base", &tried).ok()?;
+        unsafe {
+            kernel.cmd().global_work_size(batch as usize).enq().ok()?;
+        }
+        tried += batch;
 
-    for i in 0..inputs.len() {
-        let mut string = String::new();
-        for j in 0..6 {
-            if vec[(i * 6) + j] != 0 {
-                string.push(vec[i * 6 + j] as char);
+        let previously_found = found[0];
+        done.read(&mut found).enq().ok()?;
+        if found[0] != previously_found {
+            outputs.read(&mut vec).enq().ok()?;
+            for (output, found_at) in vec.chunks_exact(output_size).zip(found_at.iter_mut()) {
+                if found_at.is_none() && output.iter().any(|&b| b != 0) {
+                    *found_at = Some((start.elapsed(), tried));
+                }
             }
         }
-        results.push(string);
     }
 
+    let elapsed = start.elapsed();
+    let results: Vec<CrackResult> = vec
+        .chunks_exact(output_size)
+        .zip(job.targets())
+        .zip(found_at)
+        .map(|((output, target), found_at)| {
+            let length = output.iter().rposition(|&b| b != 0).map_or(0, |last| last + 1);
+            let (elapsed, candidates_tried) = found_at.unwrap_or((elapsed, tried));
+            CrackResult {
+                hash: target.clone(),
+                // Nothing is written for a target that isn't found
+                plaintext: Some(String::from_utf8_lossy(&output[..length]).into_owned()).filter(|p| !p.is_empty()),
+                elapsed,
+                candidates_tried,
+            }
+        })
+        .collect();
+
     if !results.is_empty() {
         Some(results)
     } else {
@@ -101,20 +164,104 @@ pub fn crack(inputs: &[&str]) -> Option<Vec<String>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::sha1;
     use std::time::Instant;
 
     #[test]
     pub fn simple_crack() {
         let hash = "c2543fff3bfa6f144c2f06a7de6cd10c0b650cae";
         let password = crack(&[hash]).unwrap();
-        assert_eq!(password.first().unwrap(), "this");
+        assert_eq!(password[0].plaintext.as_deref(), Some("this"));
+    }
+
+    #[test]
+    pub fn rejects_other_hashes() {
+        let md5 = "5d41402abc4b2a76b9719d911017c592";
+        assert!(crack(&["c2543fff3bfa6f144c2f06a7de6cd10c0b650cae", md5]).is_none());
+        assert!(crack(&["not a hash"]).is_none());
+    }
+
+    #[test]
+    pub fn crack_configured_job() {
+        let job = CrackJobBuilder::new()
+            .alphabet("aBé!".chars())
+            .min_length(2)
+            .max_length(5)
+            .targets(vec![
+                "c67fec2f7f2f9959839c94423152cada55d1c3bf",
+                &HashAlgorithm::SHA1.hex_digest("Bé!a".as_bytes()),
+            ])
+            .build()
+            .unwrap();
+        let results = crack_job(&job).unwrap();
+        assert_eq!(results[0].hash, "c67fec2f7f2f9959839c94423152cada55d1c3bf");
+        assert_eq!(results[0].plaintext, None);
+        assert_eq!(results[1].plaintext.as_deref(), Some("Bé!a"));
+        assert_eq!(results[1].candidates_tried, job.keyspace());
+    }
+
+    #[test]
+    pub fn counts_candidates_to_the_batch_a_target_is_found_in() {
+        // "a" is the very first candidate, and the keyspace takes several batches
+        let job = CrackJobBuilder::new()
+            .alphabet("abcd".chars())
+            .max_length(13)
+            .targets(vec![
+                HashAlgorithm::SHA1.hex_digest(b"a").as_str(),
+                HashAlgorithm::SHA1.hex_digest(b"missing").as_str(),
+            ])
+            .build()
+            .unwrap();
+        assert!(job.keyspace() > 4 * BATCH_SIZE);
+        let results = crack_job(&job).unwrap();
+        assert_eq!(results[0].plaintext.as_deref(), Some("a"));
+        assert_eq!(results[0].candidates_tried, BATCH_SIZE);
+        assert_eq!(results[1].candidates_tried, job.keyspace());
+        assert!(results[0].elapsed <= results[1].elapsed);
+    }
+
+    #[test]
+    pub fn rejects_keyspaces_past_the_kernel_counter() {
+        let job = CrackJobBuilder::new()
+            .max_length(16)
+            .target("c2543fff3bfa6f144c2f06a7de6cd10c0b650cae")
+            .build()
+            .unwrap();
+        assert!(job.keyspace() > i64::MAX as u64);
+        assert!(crack_job(&job).is_none());
+    }
+
+    #[test]
+    pub fn kernel_matches_sha1_across_block_boundaries() {
+        // A one character alphabet makes a job with a single candidate, so the kernel has to hash
+        // exactly that message to the digest the CPU implementation gives it. The lengths cover
+        // the last one padded within a block, the first that needs a second, whole blocks, and
+        // multi-byte characters.
+        let messages = [('x', 55), ('x', 56), ('x', 64), ('x', 119), ('é', 28), ('€', 22)];
+        for &(c, length) in messages.iter() {
+            let message = c.to_string().repeat(length);
+            let job = CrackJobBuilder::new()
+                .alphabet(vec![c])
+                .length(length)
+                .target(&sha1::to_hex(sha1::sha1(message.as_bytes())))
+                .build()
+                .unwrap();
+            assert_eq!(job.keyspace(), 1);
+            let results = crack_job(&job).unwrap();
+            assert_eq!(
+                results[0].plaintext.as_deref(),
+                Some(message.as_str()),
+                "{} bytes",
+                message.len()
+            );
+        }
     }
 
     #[test]
     pub fn crack_hello() {
         let hash = "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d";
         let password = crack(&[hash]).unwrap();
-        assert_eq!(password.first().unwrap(), "hello");
+        assert_eq!(password[0].plaintext.as_deref(), Some("hello"));
     }
 
     #[test]
@@ -126,7 +273,7 @@ mod tests {
             "Time to enumerate all passwords on GPU: {}s",
             now.elapsed().as_secs_f32()
         );
-        assert_eq!(password.first().unwrap(), "8aaaaa");
+        assert_eq!(password[0].plaintext.as_deref(), Some("8aaaaa"));
     }
 
     #[test]
@@ -145,7 +292,11 @@ mod tests {
             "02285af8f969dc5c7b12be72fbce858997afe80a",
             "57864da96344366865dd7cade69467d811a7961b",
         ];
-        let passwords = crack(&hashes).unwrap();
+        let passwords: Vec<String> = crack(&hashes)
+            .unwrap()
+            .into_iter()
+            .filter_map(|result| result.plaintext)
+            .collect();
         assert!(passwords.contains(&String::from("this")));
         assert!(passwords.contains(&String::from("is")));
         assert!(passwords.contains(&String::from("very")));
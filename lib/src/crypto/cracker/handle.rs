@@ -0,0 +1,227 @@
+//! Jobs running in the background. [`cpu::start`](super::cpu::start) hands back a [`CrackHandle`]
+//! straight away, which can be polled for the job's [`Progress`], or send it on a channel, while
+//! the workers run. Cancelling a job stops the workers at their next candidate, and its results
+//! still have anything found before then.
+
+use super::job::{CrackJob, CrackResult};
+use super::targets::Targets;
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A snapshot of how far a job has got. Workers report the candidates they've tried in batches, so
+/// `tried` lags a little behind while they're running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub tried: u64,
+    pub keyspace: u64,
+    /// Targets found so far, out of `targets`.
+    pub found: usize,
+    pub targets: usize,
+    pub elapsed: Duration,
+    /// Whether the workers have stopped, because the job is complete or was cancelled.
+    pub finished: bool,
+}
+
+impl Progress {
+    /// The share of the keyspace tried so far, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        if self.keyspace == 0 {
+            return 1.0;
+        }
+        (self.tried as f64 / self.keyspace as f64).min(1.0)
+    }
+
+    /// Candidates tried per second.
+    pub fn rate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.tried as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// The time left at the current rate to try the rest of the keyspace, `None` before there's a
+    /// rate to go by or if it's too long to represent. A job can finish sooner, once every target
+    /// is found.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.finished {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate();
+        if rate == 0.0 {
+            return None;
+        }
+        let seconds = self.keyspace.saturating_sub(self.tried) as f64 / rate;
+        if seconds.is_finite() && seconds >= 0.0 && seconds < u64::MAX as f64 {
+            Some(Duration::from_secs_f64(seconds))
+        } else {
+            None
+        }
+    }
+}
+
+/// A job running on background threads.
+pub struct CrackHandle {
+    targets: Arc<Targets>,
+    done: Arc<AtomicBool>,
+    keyspace: u64,
+    thread: JoinHandle<Vec<CrackResult>>,
+}
+
+impl CrackHandle {
+    /// Starts `run` on its own thread. It should return once the job is done or `done` is set.
+    pub(super) fn spawn(job: CrackJob, run: fn(&CrackJob, &Arc<Targets>, &Arc<AtomicBool>)) -> Self {
+        let targets = Arc::new(Targets::new(&job));
+        let done = Arc::new(AtomicBool::from(false));
+        let keyspace = job.keyspace();
+
+        let thread = {
+            let targets = targets.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                run(&job, &targets, &done);
+                targets.finish()
+            })
+        };
+
+        Self {
+            targets,
+            done,
+            keyspace,
+            thread,
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.targets.progress(self.keyspace)
+    }
+
+    /// Candidates tried per second so far.
+    pub fn rate(&self) -> f64 {
+        self.progress().rate()
+    }
+
+    /// See [`Progress::eta`].
+    pub fn eta(&self) -> Option<Duration> {
+        self.progress().eta()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress().finished
+    }
+
+    /// Asks the workers to stop. It returns straight away, [`join`](Self::join) to wait for them.
+    pub fn cancel(&self) {
+        self.done.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits for the job to finish, returning a result for each target in the order they were
+    /// given. Targets not found by a cancelled job come back without a plaintext.
+    pub fn join(self) -> Vec<CrackResult> {
+        self.thread.join().unwrap()
+    }
+
+    /// Sends the job's progress every `interval` until it finishes, the last update being the
+    /// finished one. Updates stop early if the receiver is dropped.
+    pub fn updates(&self, interval: Duration) -> Receiver<Progress> {
+        let (sender, receiver) = mpsc::channel();
+        let targets = self.targets.clone();
+        let keyspace = self.keyspace;
+        thread::spawn(move || loop {
+            let progress = targets.progress(keyspace);
+            let finished = progress.finished;
+            if sender.send(progress).is_err() || finished {
+                break;
+            }
+            thread::sleep(interval);
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::cracker::cpu;
+    use crate::crypto::cracker::job::CrackJobBuilder;
+    use crate::crypto::hash::HashAlgorithm;
+
+    #[test]
+    pub fn reports_until_finished() {
+        let job = CrackJobBuilder::new()
+            .alphabet("abcd".chars())
+            .max_length(6)
+            .targets(vec![
+                HashAlgorithm::SHA1.hex_digest(b"dcba").as_str(),
+                HashAlgorithm::SHA1.hex_digest(b"missing").as_str(),
+            ])
+            .build()
+            .unwrap();
+        let handle = cpu::start(job.clone());
+        let updates: Vec<Progress> = handle.updates(Duration::from_millis(1)).iter().collect();
+        assert!(handle.is_finished());
+        let results = handle.join();
+
+        let last = updates.last().unwrap();
+        assert!(last.finished);
+        assert_eq!((last.found, last.targets), (1, 2));
+        assert_eq!(last.tried, job.keyspace());
+        assert_eq!(last.fraction(), 1.0);
+        assert_eq!(last.eta(), Some(Duration::ZERO));
+        assert!(updates.iter().all(|update| update.keyspace == job.keyspace()));
+        assert!(updates.windows(2).all(|pair| pair[0].tried <= pair[1].tried));
+
+        assert_eq!(results[0].plaintext.as_deref(), Some("dcba"));
+        assert_eq!(results[1].candidates_tried, job.keyspace());
+        assert_eq!(results[1].elapsed, last.elapsed);
+    }
+
+    #[test]
+    pub fn cancels_a_running_job() {
+        // Trillions of candidates, far more than the test will wait for
+        let job = CrackJobBuilder::new()
+            .max_length(8)
+            .target(&HashAlgorithm::SHA1.hex_digest(b"missing"))
+            .build()
+            .unwrap();
+        let handle = cpu::start(job.clone());
+        let running = handle
+            .updates(Duration::from_millis(5))
+            .iter()
+            .find(|progress| progress.tried > 0)
+            .unwrap();
+        assert!(!running.finished);
+        assert!(running.rate() > 0.0);
+        assert!(running.fraction() < 1.0);
+        assert!(running.eta().unwrap() > Duration::from_secs(1));
+
+        handle.cancel();
+        let results = handle.join();
+        assert_eq!(results[0].plaintext, None);
+        assert!(results[0].candidates_tried < job.keyspace());
+    }
+
+    #[test]
+    pub fn eta_out_of_range() {
+        let mut progress = Progress {
+            tried: 1,
+            keyspace: u64::MAX,
+            found: 0,
+            targets: 1,
+            elapsed: Duration::from_secs(1_000_000),
+            finished: false,
+        };
+        assert_eq!(progress.eta(), None);
+
+        progress.elapsed = Duration::from_secs(1);
+        progress.keyspace = 1001;
+        assert_eq!(progress.eta(), Some(Duration::from_secs(1000)));
+    }
+}
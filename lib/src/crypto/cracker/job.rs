@@ -0,0 +1,407 @@
+use super::dictionary::Wordlist;
+use super::mask::Mask;
+use super::rules::Rule;
+use crate::crypto::hash::HashAlgorithm;
+use crate::crypto::identify;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_ALPHABET: [char; 36] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w',
+    'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+
+pub const DEFAULT_MAX_LENGTH: usize = 6;
+
+#[derive(Debug, PartialEq)]
+pub enum CrackJobError {
+    EmptyAlphabet,
+    DuplicateCharacter(char),
+    InvalidLengthRange(usize, usize),
+    NoTargets,
+    /// The target isn't a digest of the job's algorithm.
+    InvalidTarget(String),
+    UnreadableWordlist(PathBuf),
+}
+
+impl fmt::Display for CrackJobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// How a job comes up with candidates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attack {
+    /// Every string of the job's alphabet and length range.
+    BruteForce,
+    /// Every word of a wordlist put through every rule.
+    Dictionary {
+        wordlist: PathBuf,
+        rules: Vec<Rule>,
+        /// The number of words in the list when the job was built.
+        words: u64,
+    },
+    /// Every candidate of a mask.
+    Mask(Mask),
+    /// Every word of a wordlist put through every rule, then combined with every candidate of a
+    /// mask.
+    Hybrid {
+        wordlist: PathBuf,
+        rules: Vec<Rule>,
+        mask: Mask,
+        mode: HybridMode,
+        words: u64,
+    },
+}
+
+/// Which side of the words a hybrid attack puts the mask on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HybridMode {
+    /// `password?d?d`, hashcat's `-a 6`.
+    WordlistMask,
+    /// `?d?dpassword`, hashcat's `-a 7`.
+    MaskWordlist,
+}
+
+/// Builds a [`CrackJob`]. By default candidates are one to six characters of lower case letters
+/// and digits, hashed with SHA-1.
+pub struct CrackJobBuilder {
+    algorithm: HashAlgorithm,
+    alphabet: Vec<char>,
+    min_length: usize,
+    max_length: usize,
+    targets: Vec<String>,
+    wordlist: Option<PathBuf>,
+    rules: Vec<Rule>,
+    mask: Option<Mask>,
+    hybrid_mode: HybridMode,
+}
+
+impl CrackJobBuilder {
+    pub fn new() -> Self {
+        Self {
+            algorithm: HashAlgorithm::SHA1,
+            alphabet: DEFAULT_ALPHABET.to_vec(),
+            min_length: 1,
+            max_length: DEFAULT_MAX_LENGTH,
+            targets: Vec::new(),
+            wordlist: None,
+            rules: Vec::new(),
+            mask: None,
+            hybrid_mode: HybridMode::WordlistMask,
+        }
+    }
+
+    pub fn algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// The characters candidates are made of. Any characters can be used, they're hashed as UTF-8.
+    pub fn alphabet<A: IntoIterator<Item = char>>(mut self, alphabet: A) -> Self {
+        self.alphabet = alphabet.into_iter().collect();
+        self
+    }
+
+    pub fn min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Only tries candidates of exactly `length` characters.
+    pub fn length(self, length: usize) -> Self {
+        self.min_length(length).max_length(length)
+    }
+
+    pub fn target(mut self, target: &str) -> Self {
+        self.targets.push(String::from(target));
+        self
+    }
+
+    pub fn targets<'a, T: IntoIterator<Item = &'a str>>(mut self, targets: T) -> Self {
+        self.targets.extend(targets.into_iter().map(String::from));
+        self
+    }
+
+    /// Makes this a dictionary attack on the words in the file at `path`, instead of brute force.
+    pub fn wordlist<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.wordlist = Some(path.into());
+        self
+    }
+
+    /// The rules a dictionary attack applies to each word. Without any, words are tried as they are.
+    pub fn rules(mut self, rules: Vec<Rule>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Makes this a mask attack, instead of brute force. The mask sets the candidates' length and
+    /// characters, so the alphabet and length range aren't used. Along with a wordlist, it makes a
+    /// hybrid attack.
+    pub fn mask(mut self, mask: Mask) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Where a hybrid attack puts the mask, after the words by default.
+    pub fn hybrid_mode(mut self, mode: HybridMode) -> Self {
+        self.hybrid_mode = mode;
+        self
+    }
+
+    pub fn build(self) -> Result<CrackJob, CrackJobError> {
+        if self.alphabet.is_empty() {
+            return Err(CrackJobError::EmptyAlphabet);
+        }
+        let mut seen = HashSet::new();
+        if let Some(&duplicate) = self.alphabet.iter().find(|&&c| !seen.insert(c)) {
+            return Err(CrackJobError::DuplicateCharacter(duplicate));
+        }
+        if self.min_length > self.max_length || self.max_length == 0 {
+            return Err(CrackJobError::InvalidLengthRange(self.min_length, self.max_length));
+        }
+        if self.targets.is_empty() {
+            return Err(CrackJobError::NoTargets);
+        }
+
+        let digests = self
+            .targets
+            .iter()
+            .map(|target| {
+                parse_target(self.algorithm, target).ok_or_else(|| CrackJobError::InvalidTarget(target.clone()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let attack = match self.wordlist {
+            Some(wordlist) => {
+                let words = match Wordlist::open(&wordlist) {
+                    Ok(opened) => opened.word_count(),
+                    Err(_) => return Err(CrackJobError::UnreadableWordlist(wordlist)),
+                };
+                let rules = if self.rules.is_empty() {
+                    vec![Rule::identity()]
+                } else {
+                    self.rules
+                };
+                match self.mask {
+                    Some(mask) => Attack::Hybrid {
+                        wordlist,
+                        rules,
+                        mask,
+                        mode: self.hybrid_mode,
+                        words,
+                    },
+                    None => Attack::Dictionary { wordlist, rules, words },
+                }
+            }
+            None => match self.mask {
+                Some(mask) => Attack::Mask(mask),
+                None => Attack::BruteForce,
+            },
+        };
+
+        Ok(CrackJob {
+            algorithm: self.algorithm,
+            attack,
+            alphabet: self.alphabet,
+            min_length: self.min_length,
+            max_length: self.max_length,
+            targets: self.targets,
+            digests,
+        })
+    }
+}
+
+impl Default for CrackJobBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads a target as a hex digest, or any other unsalted format `identify` knows, as long as it's
+/// the right length for `algorithm`. Hex is tried first since digests of the same length are
+/// indistinguishable, and the job's algorithm settles which one is meant.
+fn parse_target(algorithm: HashAlgorithm, target: &str) -> Option<Vec<u8>> {
+    let target = target.trim();
+    let digest = match hex::decode(target) {
+        Ok(digest) => digest,
+        Err(_) => match identify::unsalted_digest(target)? {
+            (_, identified, digest) if identified == algorithm => digest,
+            _ => return None,
+        },
+    };
+    if digest.len() == algorithm.output_len() {
+        Some(digest)
+    } else {
+        None
+    }
+}
+
+/// A search for the plaintexts of some targets, all hashed with one algorithm. By default it's a
+/// brute force of every string of `min_length` to `max_length` characters from the alphabet,
+/// shortest first; other attacks come up with candidates in their own way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrackJob {
+    algorithm: HashAlgorithm,
+    attack: Attack,
+    alphabet: Vec<char>,
+    min_length: usize,
+    max_length: usize,
+    targets: Vec<String>,
+    digests: Vec<Vec<u8>>,
+}
+
+impl CrackJob {
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    pub fn attack(&self) -> &Attack {
+        &self.attack
+    }
+
+    pub fn alphabet(&self) -> &[char] {
+        &self.alphabet
+    }
+
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// The targets as they were given.
+    pub fn targets(&self) -> &[String] {
+        &self.targets
+    }
+
+    /// The raw digests of the targets, in the same order.
+    pub fn digests(&self) -> &[Vec<u8>] {
+        &self.digests
+    }
+
+    /// The number of candidates the job covers, saturating at `u64::MAX`.
+    pub fn keyspace(&self) -> u64 {
+        match &self.attack {
+            Attack::BruteForce => {
+                let size = self.alphabet.len() as u64;
+                (self.min_length..=self.max_length)
+                    .map(|length| size.checked_pow(length as u32).unwrap_or(u64::MAX))
+                    .fold(0u64, u64::saturating_add)
+            }
+            Attack::Dictionary { rules, words, .. } => words.saturating_mul(rules.len() as u64),
+            Attack::Mask(mask) => mask.keyspace(),
+            Attack::Hybrid { rules, mask, words, .. } => {
+                words.saturating_mul(rules.len() as u64).saturating_mul(mask.keyspace())
+            }
+        }
+    }
+}
+
+/// What a job found for one of its targets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrackResult {
+    /// The target as it was given.
+    pub hash: String,
+    /// Wordlist plaintexts that aren't UTF-8 are given as `$HEX[...]`, see [`Wordlist`].
+    pub plaintext: Option<String>,
+    /// Time from the start of the job until the target was found, or the whole run if it wasn't.
+    pub elapsed: Duration,
+    /// Candidates tried by the time the target was found, or in total if it wasn't.
+    pub candidates_tried: u64,
+}
+
+impl CrackResult {
+    /// A result for a target nothing has been tried against yet.
+    pub fn new(hash: &str) -> Self {
+        Self {
+            hash: String::from(hash),
+            plaintext: None,
+            elapsed: Duration::default(),
+            candidates_tried: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn builder_defaults_and_validation() {
+        let job = CrackJobBuilder::new()
+            .target("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d")
+            .build()
+            .unwrap();
+        assert_eq!(job.algorithm(), HashAlgorithm::SHA1);
+        assert_eq!(job.alphabet(), &DEFAULT_ALPHABET[..]);
+        assert_eq!((job.min_length(), job.max_length()), (1, 6));
+        assert_eq!(job.digests()[0], HashAlgorithm::SHA1.digest(b"hello"));
+
+        let builder = || CrackJobBuilder::new().target("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d");
+        assert_eq!(builder().alphabet(vec![]).build(), Err(CrackJobError::EmptyAlphabet));
+        assert_eq!(
+            builder().alphabet("abca".chars()).build(),
+            Err(CrackJobError::DuplicateCharacter('a'))
+        );
+        assert_eq!(
+            builder().min_length(5).max_length(4).build(),
+            Err(CrackJobError::InvalidLengthRange(5, 4))
+        );
+        assert_eq!(CrackJobBuilder::new().build(), Err(CrackJobError::NoTargets));
+        assert_eq!(
+            builder().algorithm(HashAlgorithm::SHA256).build(),
+            Err(CrackJobError::InvalidTarget(String::from(
+                "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"
+            )))
+        );
+    }
+
+    #[test]
+    pub fn target_formats() {
+        let job = CrackJobBuilder::new()
+            .targets(vec![
+                "{SHA}qvTGHdzF6KLavt4PO0gs2a6pQ00=",
+                "AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D",
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(job.digests()[0], job.digests()[1]);
+
+        // A 64 character digest could be any of several algorithms, the job decides which
+        let blake3 = HashAlgorithm::BLAKE3.hex_digest(b"x");
+        assert!(CrackJobBuilder::new()
+            .algorithm(HashAlgorithm::BLAKE3)
+            .target(&blake3)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    pub fn keyspace_size() {
+        let job = |min, max| {
+            CrackJobBuilder::new()
+                .alphabet("ab€".chars())
+                .min_length(min)
+                .max_length(max)
+                .target("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d")
+                .build()
+                .unwrap()
+        };
+        assert_eq!(job(0, 2).keyspace(), 1 + 3 + 9);
+        assert_eq!(job(2, 2).keyspace(), 9);
+        assert_eq!(job(1, 100).keyspace(), u64::MAX);
+    }
+}
@@ -2,8 +2,20 @@
 
 #define ROTLEFT(a, b) ((a << b) | (a >> (32 - b)))
 
+// The host defines these from the crack job before building the program
+#ifndef ALPHABET_SIZE
 #define ALPHABET_SIZE 36
+#endif
+#ifndef PASSWORD_SIZE
 #define PASSWORD_SIZE 6
+#endif
+#ifndef MIN_PASSWORD_SIZE
+#define MIN_PASSWORD_SIZE 1
+#endif
+
+// Each alphabet entry is a UTF-8 character of up to four bytes
+#define CHAR_WIDTH 4
+#define OUTPUT_SIZE (PASSWORD_SIZE * CHAR_WIDTH)
 
 typedef unsigned char BYTE; // 8-bit byte
 typedef unsigned int WORD;  // 32-bit word, change to "long" for 16-bit machines
@@ -21,28 +33,28 @@ void sha1_update(SHA1Context *ctx, const BYTE data[], size_t len);
 void sha1_final(SHA1Context *ctx, BYTE hash[]);
 
 int indices_to_string(int *indices, BYTE *string, int len,
-                      __global char *alphabet);
-bool increment_indices(char *indices, int len, int amount);
-
-__kernel void add(__global char *output, __global char *alphabet,
-                  __global BYTE *target, __global BYTE *done,
-                  __global BYTE *num_targets) {
+                      __global BYTE *alphabet,
+                      __global BYTE *alphabet_lengths);
+
+// Each launch covers part of the keyspace, starting from candidate number base
+__kernel void add(__global BYTE *output, __global BYTE *alphabet,
+                  __global BYTE *alphabet_lengths, __global BYTE *target,
+                  __global WORD *done, __global WORD *num_targets,
+                  ulong base) {
   if (*done != *num_targets) {
-    int indices[] = {-1, -1, -1, -1, -1, -1};
-    BYTE string[] = {"\0\0\0\0\0\0"};
-    char digest[40];
+    // the first candidate: blanks for the optional characters, then the
+    // shortest password allowed
+    int indices[PASSWORD_SIZE];
+    for (int i = 0; i < PASSWORD_SIZE; i++) {
+      indices[i] = i < PASSWORD_SIZE - MIN_PASSWORD_SIZE ? -1 : 0;
+    }
+    BYTE string[OUTPUT_SIZE];
 
     // increment our indices until we get to the value we want this thread to
     // test
 
-    int len = 6;
-    int amount = get_global_id(0) + 1;
-
-    if (amount == 0) {
-      return;
-    }
-
-    int carry = amount;
+    int len = PASSWORD_SIZE;
+    long carry = base + get_global_id(0);
 
     for (int i = 0; i < len; i++) {
       int position = len - 1 - i;
@@ -50,8 +62,8 @@ __kernel void add(__global char *output, __global char *alphabet,
         break;
       }
 
-      int current_value = indices[position];
-      int new_value = current_value + carry;
+      long current_value = indices[position];
+      long new_value = current_value + carry;
 
       if (new_value >= ALPHABET_SIZE) {
         carry = new_value / ALPHABET_SIZE;
@@ -63,13 +75,15 @@ __kernel void add(__global char *output, __global char *alphabet,
       indices[position] = new_value;
     }
 
+    // past the end of the keyspace
     if (carry != 0) {
-      printf("Overflow");
+      return;
     }
 
     // finished incrementing indices
 
-    int string_len = indices_to_string(indices, string, 6, alphabet);
+    int string_len = indices_to_string(indices, string, PASSWORD_SIZE,
+                                       alphabet, alphabet_lengths);
 
     // now we want to sha1 our string
     struct SHA1Context context;
@@ -81,16 +95,15 @@ __kernel void add(__global char *output, __global char *alphabet,
     // check to see if hash is equivalent to any of our targets
     // for each target
     int pass = 1;
-    for (int i = 0; i < *num_targets * 20; i += 20) {
+    for (WORD i = 0; i < *num_targets; i++) {
       pass = 1;
       for (int j = 0; j < 20; j++) {
-        // printf("%x vs %x\n", hash[j], target[j + i]);
-        pass = pass && (hash[j] == target[j + i]);
+        pass = pass && (hash[j] == target[(i * 20) + j]);
       }
       if (pass == 1) {
-        (*done)++;
-        for (int j = 0; j < 6; j++) {
-          output[((i / 20) * 6) + j] = string[j];
+        atomic_inc(done);
+        for (int j = 0; j < OUTPUT_SIZE; j++) {
+          output[(i * OUTPUT_SIZE) + j] = j < string_len ? string[j] : 0;
         }
       }
     }
@@ -98,16 +111,18 @@ __kernel void add(__global char *output, __global char *alphabet,
 }
 
 int indices_to_string(int *indices, BYTE *string, int len,
-                      __global char *alphabet) {
-  int num_chars = 0;
+                      __global BYTE *alphabet,
+                      __global BYTE *alphabet_lengths) {
+  int num_bytes = 0;
   for (int i = 0; i < len; i++) {
     if (indices[i] != -1) {
-      string[num_chars++] = alphabet[indices[i]];
+      for (int j = 0; j < alphabet_lengths[indices[i]]; j++) {
+        string[num_bytes++] = alphabet[(indices[i] * CHAR_WIDTH) + j];
+      }
     }
   }
-  string[num_chars] = '\0';
 
-  return num_chars;
+  return num_bytes;
 }
 
 void sha1_transform(SHA1Context *ctx, const BYTE data[]) {
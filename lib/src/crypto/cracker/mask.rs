@@ -0,0 +1,357 @@
+//! Mask attacks: candidates of a fixed length where each position has its own set of characters,
+//! in the hashcat mask syntax. A mask is a string of literal characters and classes:
+//!
+//! | Class | Characters |
+//! |-------|------------|
+//! | `?l` | `abcdefghijklmnopqrstuvwxyz` |
+//! | `?u` | `ABCDEFGHIJKLMNOPQRSTUVWXYZ` |
+//! | `?d` | `0123456789` |
+//! | `?h` `?H` | `0123456789abcdef`, `0123456789ABCDEF` |
+//! | `?s` | ``␣!"#$%&'()*+,-./:;<=>?@[\]^_`{|}~`` |
+//! | `?a` | `?l?u?d?s` |
+//! | `?1` to `?4` | Custom charsets |
+//! | `??` | A literal `?` |
+//!
+//! So `?u?l?l?l?d?d` is a capitalised four letter word followed by two digits. Custom charsets are
+//! given in front of the mask as `-1 ?l?d -2 !@# ?1?1?1?2`, and can be made of classes and literal
+//! characters themselves.
+
+use super::cpu::increment_indices;
+use super::targets::{Targets, COUNT_INTERVAL};
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SPECIAL: &str = " !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// The number of custom charsets a mask can use, `?1` to `?4`.
+pub const CUSTOM_CHARSETS: usize = 4;
+
+#[derive(Debug, PartialEq)]
+pub enum MaskError {
+    EmptyMask,
+    UnknownClass(char),
+    /// A `?` at the end of the mask or a charset.
+    MissingClass,
+    /// A custom charset used without being defined, or inside another charset.
+    UndefinedCharset(char),
+    /// A `-N` option with no charset after it.
+    MissingCharset(char),
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A parsed mask: the characters allowed at each position of a candidate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+    source: String,
+    positions: Vec<Vec<char>>,
+}
+
+impl Mask {
+    /// Parses `mask`, where `?1` refers to `charsets[0]` and so on. An empty charset counts as
+    /// undefined.
+    pub fn new(mask: &str, charsets: &[&str]) -> Result<Self, MaskError> {
+        let custom = charsets
+            .iter()
+            .take(CUSTOM_CHARSETS)
+            .map(|charset| {
+                let mut chars: Vec<char> = Vec::new();
+                for c in parse(charset, &[])?.into_iter().flatten() {
+                    // Characters can turn up in more than one class, but each is only tried once
+                    if !chars.contains(&c) {
+                        chars.push(c);
+                    }
+                }
+                Ok(chars)
+            })
+            .collect::<Result<Vec<Vec<char>>, MaskError>>()?;
+
+        let positions = parse(mask, &custom)?;
+        if positions.is_empty() {
+            return Err(MaskError::EmptyMask);
+        }
+
+        let mut source = String::new();
+        for (n, charset) in charsets.iter().enumerate().filter(|(_, charset)| !charset.is_empty()) {
+            source.push_str(&format!("-{} {} ", n + 1, charset));
+        }
+        source.push_str(mask);
+
+        Ok(Self { source, positions })
+    }
+
+    /// The length of every candidate, in characters.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The characters allowed at each position.
+    pub fn positions(&self) -> &[Vec<char>] {
+        &self.positions
+    }
+
+    /// How many characters each position can be, the radices of the mask's odometer.
+    pub fn radices(&self) -> Vec<usize> {
+        self.positions.iter().map(Vec::len).collect()
+    }
+
+    /// The number of candidates the mask covers, saturating at `u64::MAX`.
+    pub fn keyspace(&self) -> u64 {
+        self.positions.iter().fold(1u64, |keyspace, position| {
+            keyspace.saturating_mul(position.len() as u64)
+        })
+    }
+
+    /// The candidate an odometer reading stands for.
+    #[inline]
+    pub fn candidate(&self, indices: &[i32]) -> String {
+        indices
+            .iter()
+            .zip(self.positions.iter())
+            .map(|(&index, position)| position[index as usize])
+            .collect()
+    }
+
+    /// Every candidate of the mask, the last position changing fastest.
+    pub fn candidates(&self) -> Candidates<'_> {
+        Candidates {
+            mask: self,
+            radices: self.radices(),
+            indices: Some(vec![0; self.len()].into_boxed_slice()),
+        }
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for Mask {
+    type Err = MaskError;
+
+    /// Parses a mask with its custom charsets in front of it, as in `-1 ?l?d ?u?1?1?1`.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut charsets = [""; CUSTOM_CHARSETS];
+        let mut rest = source.trim();
+        loop {
+            let (option, after) = next_token(rest);
+            let n = match option.strip_prefix('-').and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if (1..=CUSTOM_CHARSETS).contains(&n) => n,
+                _ => break,
+            };
+            let (charset, after) = next_token(after);
+            if charset.is_empty() {
+                return Err(MaskError::MissingCharset(option.chars().nth(1).unwrap()));
+            }
+            charsets[n - 1] = charset;
+            rest = after;
+        }
+        Self::new(rest, &charsets)
+    }
+}
+
+/// Splits off the text up to the first whitespace, returning it and what's left after the
+/// whitespace.
+fn next_token(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], text[end..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+/// The characters allowed at each position of `mask`. Charsets can't refer to custom charsets, so
+/// they're parsed with none defined.
+fn parse(mask: &str, custom: &[Vec<char>]) -> Result<Vec<Vec<char>>, MaskError> {
+    let mut positions = Vec::new();
+    let mut chars = mask.chars();
+    while let Some(c) = chars.next() {
+        if c != '?' {
+            positions.push(vec![c]);
+            continue;
+        }
+        let class = chars.next().ok_or(MaskError::MissingClass)?;
+        let position = match class {
+            'l' => LOWER.chars().collect(),
+            'u' => UPPER.chars().collect(),
+            'd' => DIGITS.chars().collect(),
+            'h' => DIGITS.chars().chain("abcdef".chars()).collect(),
+            'H' => DIGITS.chars().chain("ABCDEF".chars()).collect(),
+            's' => SPECIAL.chars().collect(),
+            'a' => LOWER
+                .chars()
+                .chain(UPPER.chars())
+                .chain(DIGITS.chars())
+                .chain(SPECIAL.chars())
+                .collect(),
+            '?' => vec!['?'],
+            '1'..='4' => match custom.get(class as usize - '1' as usize) {
+                Some(charset) if !charset.is_empty() => charset.clone(),
+                _ => return Err(MaskError::UndefinedCharset(class)),
+            },
+            _ => return Err(MaskError::UnknownClass(class)),
+        };
+        positions.push(position);
+    }
+    Ok(positions)
+}
+
+/// The candidates of a [`Mask`] in order, from [`Mask::candidates`].
+pub struct Candidates<'a> {
+    mask: &'a Mask,
+    radices: Vec<usize>,
+    /// The next candidate's odometer reading, `None` once they've all been given.
+    indices: Option<Box<[i32]>>,
+}
+
+impl Iterator for Candidates<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let indices = self.indices.as_mut()?;
+        let candidate = self.mask.candidate(indices);
+        if increment_indices(indices, &self.radices, 1).is_err() {
+            self.indices = None;
+        }
+        Some(candidate)
+    }
+}
+
+/// Runs a mask attack until it's been through the mask or `done` is set, each thread taking every
+/// `num_cpus`th candidate.
+pub(super) fn run(mask: &Mask, targets: &Arc<Targets>, done: &Arc<AtomicBool>) {
+    let mask = Arc::new(mask.clone());
+    let radices = mask.radices();
+
+    let mut handles = vec![];
+    for thread in 0..num_cpus::get() {
+        let mut indices = vec![0; mask.len()].into_boxed_slice();
+        // Keyspaces smaller than the thread count leave the extra threads with nothing to do
+        if increment_indices(&mut indices, &radices, thread as i32).is_err() {
+            break;
+        }
+        handles.push(spawn_worker_thread(
+            done.clone(),
+            indices,
+            mask.clone(),
+            targets.clone(),
+        ));
+    }
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+fn spawn_worker_thread(
+    done: Arc<AtomicBool>,
+    mut indices: Box<[i32]>,
+    mask: Arc<Mask>,
+    targets: Arc<Targets>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let radices = mask.radices();
+        let mut tried = 0;
+        loop {
+            let password = mask.candidate(&indices);
+            tried += 1;
+            if let Some(positions) = targets.lookup(password.as_bytes()) {
+                if targets.found(positions, &password, tried) {
+                    done.store(true, Ordering::SeqCst);
+                }
+            }
+
+            if tried == COUNT_INTERVAL {
+                targets.count(tried);
+                tried = 0;
+            }
+            if increment_indices(&mut indices, &radices, num_cpus::get() as i32).is_err() || done.load(Ordering::SeqCst)
+            {
+                break;
+            }
+        }
+        targets.count(tried);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::cracker::cpu;
+    use crate::crypto::cracker::job::CrackJobBuilder;
+    use crate::crypto::hash::HashAlgorithm;
+
+    #[test]
+    pub fn parses_classes() {
+        let mask: Mask = "?u?l?d?s?a".parse().unwrap();
+        assert_eq!(mask.radices(), vec![26, 26, 10, 33, 95]);
+        assert_eq!(mask.keyspace(), 26 * 26 * 10 * 33 * 95);
+
+        let mask: Mask = "-1 ?dab -2 !?? x??-?1?2?h".parse().unwrap();
+        assert_eq!(mask.len(), 6);
+        assert_eq!(mask.positions()[0], vec!['x']);
+        assert_eq!(mask.positions()[1], vec!['?']);
+        assert_eq!(mask.positions()[2], vec!['-']);
+        assert_eq!(mask.positions()[3], "0123456789ab".chars().collect::<Vec<char>>());
+        assert_eq!(mask.positions()[4], vec!['!', '?']);
+        assert_eq!(mask.radices()[5], 16);
+        assert_eq!(mask.to_string(), "-1 ?dab -2 !?? x??-?1?2?h");
+        assert_eq!(mask.to_string().parse::<Mask>(), Ok(mask));
+
+        // Duplicates within a charset are dropped
+        assert_eq!(Mask::new("?1", &["?daa1"]).unwrap().keyspace(), 11);
+        assert_eq!(Mask::new("?a?a?a?a?a?a?a?a?a?a?a", &[]).unwrap().keyspace(), u64::MAX);
+    }
+
+    #[test]
+    pub fn enumerates_in_order() {
+        let mask = Mask::new("?1?2", &["ab", "xyz"]).unwrap();
+        let candidates: Vec<String> = mask.candidates().collect();
+        assert_eq!(candidates, vec!["ax", "ay", "az", "bx", "by", "bz"]);
+        assert_eq!(candidates.len() as u64, mask.keyspace());
+    }
+
+    #[test]
+    pub fn mask_errors() {
+        assert_eq!("".parse::<Mask>(), Err(MaskError::EmptyMask));
+        assert_eq!("?l?x".parse::<Mask>(), Err(MaskError::UnknownClass('x')));
+        assert_eq!("ab?".parse::<Mask>(), Err(MaskError::MissingClass));
+        assert_eq!("?l?3".parse::<Mask>(), Err(MaskError::UndefinedCharset('3')));
+        assert_eq!("-1 ?2 ?1".parse::<Mask>(), Err(MaskError::UndefinedCharset('2')));
+        assert_eq!("-2".parse::<Mask>(), Err(MaskError::MissingCharset('2')));
+    }
+
+    #[test]
+    pub fn cracks_masked_passwords() {
+        let hash = |password: &str| HashAlgorithm::SHA1.hex_digest(password.as_bytes());
+        let hashes = [hash("Dog!"), hash("cat1"), hash("Cat7"), hash("Zzz9")];
+        let job = CrackJobBuilder::new()
+            .mask("-1 ?d! ?u?l?l?1".parse().unwrap())
+            .targets(hashes.iter().map(String::as_str))
+            .build()
+            .unwrap();
+        assert_eq!(job.keyspace(), 26 * 26 * 26 * 11);
+
+        let results = cpu::crack(&job);
+        let plaintexts: Vec<Option<&str>> = results.iter().map(|result| result.plaintext.as_deref()).collect();
+        assert_eq!(plaintexts, vec![Some("Dog!"), None, Some("Cat7"), Some("Zzz9")]);
+        assert_eq!(results[1].candidates_tried, job.keyspace());
+    }
+}
@@ -1,2 +1,8 @@
 pub mod cpu;
+pub mod dictionary;
 pub mod gpu;
+pub mod handle;
+pub mod job;
+pub mod mask;
+pub mod rules;
+mod targets;
@@ -0,0 +1,386 @@
+//! Word mangling rules in the hashcat/John the Ripper syntax. A rule is a sequence of functions,
+//! each one or a few characters, applied to a word in order:
+//!
+//! | Function | Effect |
+//! |----------|--------|
+//! | `:` | Nothing |
+//! | `l` `u` | Lower or upper case the whole word |
+//! | `c` `C` | Capitalise, or lower the first letter and upper the rest |
+//! | `t` `TN` | Toggle the case of every letter, or just the one at N |
+//! | `r` | Reverse |
+//! | `d` `pN` `f` | Duplicate, append N more copies, or append the word reversed |
+//! | `{` `}` | Rotate left or right by one |
+//! | `$X` `^X` | Append or prepend X |
+//! | `[` `]` `DN` | Delete the first, last or Nth character |
+//! | `'N` | Truncate to N characters |
+//! | `iNX` `oNX` | Insert X at N, or overwrite the character at N with it |
+//! | `sXY` `@X` | Replace every X with Y, or remove every X |
+//!
+//! Positions are `0`-`9` then `A`-`Z` for 10 to 35. As in hashcat, rules work on bytes: case
+//! changes only affect ASCII letters and arguments must be ASCII. Spaces between functions are
+//! ignored, so `sa@ se3` is the leetspeak rule replacing a and e. Words are limited to
+//! [`MAX_WORD_LEN`] bytes, and a rule that would take a word past it rejects the word instead.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The longest word rules work on or produce, as in hashcat.
+pub const MAX_WORD_LEN: usize = 256;
+
+/// A small general purpose rule set: the word as it is, common case changes, reversal and
+/// duplication, digits and punctuation on the end, and leetspeak.
+pub const DEFAULT_RULES: [&str; 16] = [
+    ":",
+    "l",
+    "u",
+    "c",
+    "t",
+    "r",
+    "d",
+    "f",
+    "$1",
+    "$!",
+    "c $1",
+    "$1 $2 $3",
+    "c $1 $2 $3",
+    "^1",
+    "sa@ se3 si1 so0",
+    "c sa@ se3 si1 so0 $!",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum RuleError {
+    UnknownFunction(char),
+    MissingArgument(char),
+    InvalidPosition(char),
+    NonAsciiArgument(char),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Function {
+    Nothing,
+    Lower,
+    Upper,
+    Capitalise,
+    InvertCapitalise,
+    ToggleAll,
+    Toggle(usize),
+    Reverse,
+    Duplicate,
+    DuplicateTimes(usize),
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(u8),
+    Prepend(u8),
+    DeleteFirst,
+    DeleteLast,
+    Delete(usize),
+    Truncate(usize),
+    Insert(usize, u8),
+    Overwrite(usize, u8),
+    Replace(u8, u8),
+    Purge(u8),
+}
+
+impl Function {
+    /// The most bytes applying this to a word of `length` bytes can leave it with.
+    fn max_len(self, length: usize) -> usize {
+        match self {
+            Function::Duplicate | Function::Reflect => length.saturating_mul(2),
+            Function::DuplicateTimes(n) => length.saturating_mul(n + 1),
+            Function::Append(_) | Function::Prepend(_) | Function::Insert(..) => length + 1,
+            _ => length,
+        }
+    }
+
+    fn apply(self, word: &mut Vec<u8>) {
+        match self {
+            Function::Nothing => {}
+            Function::Lower => word.make_ascii_lowercase(),
+            Function::Upper => word.make_ascii_uppercase(),
+            Function::Capitalise => {
+                word.make_ascii_lowercase();
+                if let Some(first) = word.first_mut() {
+                    first.make_ascii_uppercase();
+                }
+            }
+            Function::InvertCapitalise => {
+                word.make_ascii_uppercase();
+                if let Some(first) = word.first_mut() {
+                    first.make_ascii_lowercase();
+                }
+            }
+            Function::ToggleAll => word.iter_mut().for_each(|byte| *byte = toggle_case(*byte)),
+            Function::Toggle(n) => {
+                if let Some(byte) = word.get_mut(n) {
+                    *byte = toggle_case(*byte);
+                }
+            }
+            Function::Reverse => word.reverse(),
+            Function::Duplicate => word.extend_from_within(..),
+            Function::DuplicateTimes(n) => {
+                let original = word.len();
+                for _ in 0..n {
+                    word.extend_from_within(..original);
+                }
+            }
+            Function::Reflect => {
+                let reversed: Vec<u8> = word.iter().rev().cloned().collect();
+                word.extend(reversed);
+            }
+            Function::RotateLeft => {
+                if !word.is_empty() {
+                    word.rotate_left(1);
+                }
+            }
+            Function::RotateRight => {
+                if !word.is_empty() {
+                    word.rotate_right(1);
+                }
+            }
+            Function::Append(byte) => word.push(byte),
+            Function::Prepend(byte) => word.insert(0, byte),
+            Function::DeleteFirst => {
+                if !word.is_empty() {
+                    word.remove(0);
+                }
+            }
+            Function::DeleteLast => {
+                word.pop();
+            }
+            Function::Delete(n) => {
+                if n < word.len() {
+                    word.remove(n);
+                }
+            }
+            Function::Truncate(n) => word.truncate(n),
+            Function::Insert(n, byte) => {
+                if n <= word.len() {
+                    word.insert(n, byte);
+                }
+            }
+            Function::Overwrite(n, byte) => {
+                if let Some(existing) = word.get_mut(n) {
+                    *existing = byte;
+                }
+            }
+            Function::Replace(from, to) => word
+                .iter_mut()
+                .filter(|byte| **byte == from)
+                .for_each(|byte| *byte = to),
+            Function::Purge(byte) => word.retain(|&b| b != byte),
+        }
+    }
+}
+
+fn toggle_case(byte: u8) -> u8 {
+    if byte.is_ascii_alphabetic() {
+        byte ^ 0x20
+    } else {
+        byte
+    }
+}
+
+/// A parsed rule, applied with [`Rule::apply`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    source: String,
+    functions: Vec<Function>,
+}
+
+impl Rule {
+    /// The rule that leaves words as they are.
+    pub fn identity() -> Self {
+        Self {
+            source: String::from(":"),
+            functions: vec![Function::Nothing],
+        }
+    }
+
+    /// Mangles `word`, or rejects it with `None` if it's longer than [`MAX_WORD_LEN`] or the rule
+    /// would make it so. The check comes before each function, so a rule like `dddddddddd` never
+    /// builds the oversized word.
+    pub fn apply(&self, word: &[u8]) -> Option<Vec<u8>> {
+        if word.len() > MAX_WORD_LEN {
+            return None;
+        }
+        let mut word = word.to_vec();
+        for function in self.functions.iter() {
+            if function.max_len(word.len()) > MAX_WORD_LEN {
+                return None;
+            }
+            function.apply(&mut word);
+        }
+        Some(word)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn position(name: char, argument: Option<char>) -> Result<usize, RuleError> {
+    match argument {
+        Some(c @ '0'..='9') => Ok(c as usize - '0' as usize),
+        Some(c @ 'A'..='Z') => Ok(c as usize - 'A' as usize + 10),
+        Some(c) => Err(RuleError::InvalidPosition(c)),
+        None => Err(RuleError::MissingArgument(name)),
+    }
+}
+
+fn byte(name: char, argument: Option<char>) -> Result<u8, RuleError> {
+    match argument {
+        Some(c) if c.is_ascii() => Ok(c as u8),
+        Some(c) => Err(RuleError::NonAsciiArgument(c)),
+        None => Err(RuleError::MissingArgument(name)),
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut functions = Vec::new();
+        let mut chars = source.chars();
+        while let Some(name) = chars.next() {
+            let function = match name {
+                // Only skipped between functions, since a space is a valid argument
+                ' ' | '\t' => continue,
+                ':' => Function::Nothing,
+                'l' => Function::Lower,
+                'u' => Function::Upper,
+                'c' => Function::Capitalise,
+                'C' => Function::InvertCapitalise,
+                't' => Function::ToggleAll,
+                'T' => Function::Toggle(position(name, chars.next())?),
+                'r' => Function::Reverse,
+                'd' => Function::Duplicate,
+                'p' => Function::DuplicateTimes(position(name, chars.next())?),
+                'f' => Function::Reflect,
+                '{' => Function::RotateLeft,
+                '}' => Function::RotateRight,
+                '$' => Function::Append(byte(name, chars.next())?),
+                '^' => Function::Prepend(byte(name, chars.next())?),
+                '[' => Function::DeleteFirst,
+                ']' => Function::DeleteLast,
+                'D' => Function::Delete(position(name, chars.next())?),
+                '\'' => Function::Truncate(position(name, chars.next())?),
+                'i' => Function::Insert(position(name, chars.next())?, byte(name, chars.next())?),
+                'o' => Function::Overwrite(position(name, chars.next())?, byte(name, chars.next())?),
+                's' => Function::Replace(byte(name, chars.next())?, byte(name, chars.next())?),
+                '@' => Function::Purge(byte(name, chars.next())?),
+                _ => return Err(RuleError::UnknownFunction(name)),
+            };
+            functions.push(function);
+        }
+
+        Ok(Self {
+            source: String::from(source),
+            functions,
+        })
+    }
+}
+
+/// Parses a rule file, one rule per line. Blank lines and lines starting with `#` are skipped.
+pub fn parse_rules(text: &str) -> Result<Vec<Rule>, RuleError> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(str::parse)
+        .collect()
+}
+
+pub fn default_rules() -> Vec<Rule> {
+    DEFAULT_RULES.iter().map(|rule| rule.parse().unwrap()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rule: &str, word: &str) -> String {
+        String::from_utf8(rule.parse::<Rule>().unwrap().apply(word.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    pub fn hashcat_functions() {
+        let cases = [
+            (":", "p@ssW0rd", "p@ssW0rd"),
+            ("l", "p@ssW0rd", "p@ssw0rd"),
+            ("u", "p@ssW0rd", "P@SSW0RD"),
+            ("c", "p@ssW0rd", "P@ssw0rd"),
+            ("C", "p@ssW0rd", "p@SSW0RD"),
+            ("t", "p@ssW0rd", "P@SSw0RD"),
+            ("T0", "p@ssW0rd", "P@ssW0rd"),
+            ("T9", "p@ssW0rd", "p@ssW0rd"),
+            ("r", "p@ssW0rd", "dr0Wss@p"),
+            ("d", "p@ssW0rd", "p@ssW0rdp@ssW0rd"),
+            ("p2", "abc", "abcabcabc"),
+            ("f", "abc", "abccba"),
+            ("{", "p@ssW0rd", "@ssW0rdp"),
+            ("}", "p@ssW0rd", "dp@ssW0r"),
+            ("$1", "p@ssW0rd", "p@ssW0rd1"),
+            ("^1", "p@ssW0rd", "1p@ssW0rd"),
+            ("$ ", "a", "a "),
+            ("[", "p@ssW0rd", "@ssW0rd"),
+            ("]", "p@ssW0rd", "p@ssW0r"),
+            ("D3", "p@ssW0rd", "p@sW0rd"),
+            ("'4", "p@ssW0rd", "p@ss"),
+            ("i4!", "p@ssW0rd", "p@ss!W0rd"),
+            ("o0$", "p@ssW0rd", "$@ssW0rd"),
+            ("ss$", "p@ssW0rd", "p@$$W0rd"),
+            ("@s", "p@ssW0rd", "p@W0rd"),
+            ("TA", "abcdefghijkl", "abcdefghijKl"),
+        ];
+        for (rule, word, expected) in cases.iter() {
+            assert_eq!(apply(rule, word), *expected, "{}", rule);
+        }
+    }
+
+    #[test]
+    pub fn combined_rules() {
+        assert_eq!(apply("sa@ se3 si1 so0", "passwordie"), "p@ssw0rd13");
+        assert_eq!(apply("c $1 $2 $3", "monkey"), "Monkey123");
+        assert_eq!(apply("r c", "drowssap"), "Password");
+        // Functions that don't fit the word leave it alone
+        assert_eq!(apply("[ ] D5 i9x o7y", ""), "");
+    }
+
+    #[test]
+    pub fn word_length_limit() {
+        let rule = |source: &str| source.parse::<Rule>().unwrap();
+        assert_eq!(rule("dddddddddddddddddddddddddddddd").apply(b"password"), None);
+        assert_eq!(rule("p9 p9 p9").apply(b"ab"), None);
+        assert_eq!(rule("f").apply(&[b'a'; 129]), None);
+        assert_eq!(rule(":").apply(&[b'a'; MAX_WORD_LEN + 1]), None);
+
+        // Right up to the limit is fine
+        assert_eq!(rule("ddddd").apply(&[b'a'; 8]).unwrap().len(), MAX_WORD_LEN);
+        assert_eq!(rule("$!").apply(&[b'a'; MAX_WORD_LEN - 1]).unwrap().len(), MAX_WORD_LEN);
+        assert_eq!(rule("$!").apply(&[b'a'; MAX_WORD_LEN]), None);
+        // Shrinking functions don't count against it
+        assert_eq!(rule("ddddd ]").apply(&[b'a'; 8]).unwrap().len(), MAX_WORD_LEN - 1);
+    }
+
+    #[test]
+    pub fn rule_errors() {
+        assert_eq!("x".parse::<Rule>(), Err(RuleError::UnknownFunction('x')));
+        assert_eq!("$".parse::<Rule>(), Err(RuleError::MissingArgument('$')));
+        assert_eq!("T!".parse::<Rule>(), Err(RuleError::InvalidPosition('!')));
+        assert_eq!("$é".parse::<Rule>(), Err(RuleError::NonAsciiArgument('é')));
+
+        let rules = parse_rules("# comment\n:\r\n\nc $1\n").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].to_string(), "c $1");
+        assert_eq!(default_rules().len(), DEFAULT_RULES.len());
+    }
+}
@@ -0,0 +1,139 @@
+use super::handle::Progress;
+use super::job::{CrackJob, CrackResult};
+use crate::crypto::hash::HashAlgorithm;
+use crate::crypto::sha1;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Workers add up the candidates they've tried locally and only add them to the shared count this
+/// often, to keep the atomic off the hot path.
+pub(super) const COUNT_INTERVAL: u64 = 4096;
+
+/// The positions in the job of each digest, more than one if a target was given twice. SHA-1
+/// digests are kept as the five words the in-crate implementation produces, so a candidate's
+/// digest is compared without converting it to bytes.
+enum Digests {
+    Sha1(HashMap<[u32; 5], Vec<usize>>),
+    Bytes(HashMap<Vec<u8>, Vec<usize>>),
+}
+
+/// Every digest being searched for, keyed by the raw digest so each candidate is hashed once and
+/// checked against all the targets with a single lookup.
+pub(super) struct Targets {
+    algorithm: HashAlgorithm,
+    digests: Digests,
+    results: Mutex<Vec<CrackResult>>,
+    remaining: AtomicUsize,
+    start: Instant,
+    /// The run time, once the workers have stopped.
+    finished: Mutex<Option<Duration>>,
+    /// Candidates tried so far, short of what the workers haven't added yet.
+    tried: AtomicU64,
+}
+
+impl Targets {
+    pub(super) fn new(job: &CrackJob) -> Self {
+        let digests = if job.algorithm() == HashAlgorithm::SHA1 {
+            let mut digests: HashMap<[u32; 5], Vec<usize>> = HashMap::new();
+            for (position, digest) in job.digests().iter().enumerate() {
+                // Targets are checked to be the algorithm's length when the job is built
+                digests.entry(sha1::from_bytes(digest).unwrap()).or_default().push(position);
+            }
+            Digests::Sha1(digests)
+        } else {
+            let mut digests: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+            for (position, digest) in job.digests().iter().enumerate() {
+                digests.entry(digest.clone()).or_default().push(position);
+            }
+            Digests::Bytes(digests)
+        };
+
+        Self {
+            algorithm: job.algorithm(),
+            digests,
+            results: Mutex::new(job.targets().iter().map(|target| CrackResult::new(target)).collect()),
+            remaining: AtomicUsize::new(job.digests().len()),
+            start: Instant::now(),
+            finished: Mutex::new(None),
+            tried: AtomicU64::new(0),
+        }
+    }
+
+    /// The positions of the targets `candidate` hashes to, if any. SHA-1 goes through the in-crate
+    /// implementation, which hashes without allocating.
+    #[inline]
+    pub(super) fn lookup(&self, candidate: &[u8]) -> Option<&Vec<usize>> {
+        match &self.digests {
+            Digests::Sha1(digests) => digests.get(&sha1::sha1(candidate)),
+            Digests::Bytes(digests) => digests.get(&self.algorithm.digest(candidate)),
+        }
+    }
+
+    /// Whether the targets are SHA-1, so callers can hash candidates themselves and use
+    /// [`lookup_sha1`](Self::lookup_sha1).
+    pub(super) fn is_sha1(&self) -> bool {
+        matches!(self.digests, Digests::Sha1(_))
+    }
+
+    /// The positions of the SHA-1 targets with the given digest, if any.
+    #[inline]
+    pub(super) fn lookup_sha1(&self, digest: &[u32; 5]) -> Option<&Vec<usize>> {
+        match &self.digests {
+            Digests::Sha1(digests) => digests.get(digest),
+            Digests::Bytes(_) => None,
+        }
+    }
+
+    /// Records `plaintext` as the answer for the targets at `positions`, returning whether every
+    /// target has now been found. `uncounted` is the finding worker's tries not yet in `tried`.
+    pub(super) fn found(&self, positions: &[usize], plaintext: &str, uncounted: u64) -> bool {
+        let mut results = self.results.lock().unwrap();
+        for &position in positions {
+            let result = &mut results[position];
+            if result.plaintext.is_none() {
+                result.plaintext = Some(String::from(plaintext));
+                result.elapsed = self.start.elapsed();
+                result.candidates_tried = self.tried.load(Ordering::SeqCst) + uncounted;
+                self.remaining.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+        self.remaining.load(Ordering::SeqCst) == 0
+    }
+
+    /// Adds a worker's tries to the shared count.
+    pub(super) fn count(&self, tried: u64) {
+        self.tried.fetch_add(tried, Ordering::SeqCst);
+    }
+
+    /// How far the search has got through `keyspace` candidates.
+    pub(super) fn progress(&self, keyspace: u64) -> Progress {
+        let finished = *self.finished.lock().unwrap();
+        let targets = self.results.lock().unwrap().len();
+        Progress {
+            tried: self.tried.load(Ordering::SeqCst),
+            keyspace,
+            found: targets - self.remaining.load(Ordering::SeqCst),
+            targets,
+            elapsed: finished.unwrap_or_else(|| self.start.elapsed()),
+            finished: finished.is_some(),
+        }
+    }
+
+    /// The results once the workers have stopped, with the totals for the targets never found.
+    pub(super) fn finish(&self) -> Vec<CrackResult> {
+        let elapsed = *self
+            .finished
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| self.start.elapsed());
+        let mut results = self.results.lock().unwrap().clone();
+        for result in results.iter_mut().filter(|result| result.plaintext.is_none()) {
+            result.elapsed = elapsed;
+            result.candidates_tried = self.tried.load(Ordering::SeqCst);
+        }
+        results
+    }
+}
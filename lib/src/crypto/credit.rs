@@ -1,9 +1,188 @@
+use super::checkdigit;
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CreditCardVerificationError {
     InvalidCreditCard,
     InvalidDigitsFound,
     InvalidLength,
 }
 
+/// Version of the embedded IIN range table. Bump this whenever `IIN_RANGES` is edited.
+pub const IIN_TABLE_VERSION: &str = "2021.01";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum CardScheme {
+    AmericanExpress,
+    ChinaTUnion,
+    Dankort,
+    DinersClub,
+    Discover,
+    Humo,
+    InterPayment,
+    Jcb,
+    LankaPay,
+    Maestro,
+    MaestroUk,
+    Mastercard,
+    Mir,
+    RuPay,
+    Troy,
+    Uatp,
+    UnionPay,
+    UzCard,
+    Verve,
+    Visa,
+    VisaElectron,
+    Unknown,
+}
+
+impl fmt::Display for CardScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CardScheme::AmericanExpress => "American Express",
+            CardScheme::ChinaTUnion => "China T-Union",
+            CardScheme::Dankort => "Dankort",
+            CardScheme::DinersClub => "Diners Club",
+            CardScheme::Discover => "Discover",
+            CardScheme::Humo => "Humo",
+            CardScheme::InterPayment => "InterPayment",
+            CardScheme::Jcb => "JCB",
+            CardScheme::LankaPay => "LankaPay",
+            CardScheme::Maestro => "Maestro",
+            CardScheme::MaestroUk => "Maestro UK",
+            CardScheme::Mastercard => "Mastercard",
+            CardScheme::Mir => "Mir",
+            CardScheme::RuPay => "RuPay",
+            CardScheme::Troy => "Troy",
+            CardScheme::Uatp => "UATP",
+            CardScheme::UnionPay => "UnionPay",
+            CardScheme::UzCard => "UzCard",
+            CardScheme::Verve => "Verve",
+            CardScheme::Visa => "Visa",
+            CardScheme::VisaElectron => "Visa Electron",
+            CardScheme::Unknown => "Unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A contiguous block of issuer identification numbers. `low` and `high` always have the same
+/// number of digits, which is the number of leading digits of a card number they are matched against.
+pub(crate) struct IinRange {
+    pub(crate) low: u32,
+    pub(crate) high: u32,
+    pub(crate) scheme: CardScheme,
+    pub(crate) lengths: &'static [usize],
+}
+
+const fn iin(low: u32, high: u32, scheme: CardScheme, lengths: &'static [usize]) -> IinRange {
+    IinRange {
+        low,
+        high,
+        scheme,
+        lengths,
+    }
+}
+
+const LENGTHS_12_TO_19: &[usize] = &[12, 13, 14, 15, 16, 17, 18, 19];
+const LENGTHS_14_TO_19: &[usize] = &[14, 15, 16, 17, 18, 19];
+const LENGTHS_16_TO_19: &[usize] = &[16, 17, 18, 19];
+
+/// Lengths accepted for numbers whose IIN isn't in the table.
+const UNKNOWN_SCHEME_LENGTHS: &[usize] = &[16];
+
+/// Issuer ranges, based on the published scheme ranges as of the table version. Where ranges
+/// overlap the one with the longest prefix wins, so co-branded sub-ranges can be listed alongside
+/// the broader range they sit inside.
+pub(crate) const IIN_RANGES: [IinRange; 43] = [
+    iin(1, 1, CardScheme::Uatp, &[15]),
+    iin(2200, 2204, CardScheme::Mir, LENGTHS_16_TO_19),
+    iin(2221, 2720, CardScheme::Mastercard, &[16]),
+    iin(300, 305, CardScheme::DinersClub, LENGTHS_14_TO_19),
+    iin(3095, 3095, CardScheme::DinersClub, LENGTHS_14_TO_19),
+    iin(31, 31, CardScheme::ChinaTUnion, &[19]),
+    iin(34, 34, CardScheme::AmericanExpress, &[15]),
+    iin(3528, 3589, CardScheme::Jcb, LENGTHS_16_TO_19),
+    iin(357111, 357111, CardScheme::LankaPay, &[16]),
+    iin(36, 36, CardScheme::DinersClub, LENGTHS_14_TO_19),
+    iin(37, 37, CardScheme::AmericanExpress, &[15]),
+    iin(38, 39, CardScheme::DinersClub, LENGTHS_14_TO_19),
+    iin(4, 4, CardScheme::Visa, &[13, 16, 19]),
+    iin(4026, 4026, CardScheme::VisaElectron, &[16]),
+    iin(417500, 417500, CardScheme::VisaElectron, &[16]),
+    iin(4508, 4508, CardScheme::VisaElectron, &[16]),
+    iin(4844, 4844, CardScheme::VisaElectron, &[16]),
+    iin(4913, 4913, CardScheme::VisaElectron, &[16]),
+    iin(4917, 4917, CardScheme::VisaElectron, &[16]),
+    iin(5018, 5018, CardScheme::Maestro, LENGTHS_12_TO_19),
+    iin(5019, 5019, CardScheme::Dankort, &[16]),
+    iin(5020, 5020, CardScheme::Maestro, LENGTHS_12_TO_19),
+    iin(5038, 5038, CardScheme::Maestro, LENGTHS_12_TO_19),
+    iin(506099, 506198, CardScheme::Verve, &[16, 19]),
+    iin(508, 508, CardScheme::RuPay, &[16]),
+    iin(51, 55, CardScheme::Mastercard, &[16]),
+    iin(5893, 5893, CardScheme::Maestro, LENGTHS_12_TO_19),
+    iin(6011, 6011, CardScheme::Discover, LENGTHS_16_TO_19),
+    iin(62, 62, CardScheme::UnionPay, LENGTHS_16_TO_19),
+    iin(622126, 622925, CardScheme::Discover, LENGTHS_16_TO_19),
+    iin(6304, 6304, CardScheme::Maestro, LENGTHS_12_TO_19),
+    iin(636, 636, CardScheme::InterPayment, LENGTHS_16_TO_19),
+    iin(644, 649, CardScheme::Discover, LENGTHS_16_TO_19),
+    iin(65, 65, CardScheme::Discover, LENGTHS_16_TO_19),
+    iin(650002, 650027, CardScheme::Verve, &[16, 19]),
+    iin(6759, 6759, CardScheme::Maestro, LENGTHS_12_TO_19),
+    iin(676770, 676770, CardScheme::MaestroUk, LENGTHS_12_TO_19),
+    iin(676774, 676774, CardScheme::MaestroUk, LENGTHS_12_TO_19),
+    iin(6761, 6763, CardScheme::Maestro, LENGTHS_12_TO_19),
+    iin(81, 82, CardScheme::RuPay, &[16]),
+    iin(8600, 8600, CardScheme::UzCard, &[16]),
+    iin(9792, 9792, CardScheme::Troy, &[16]),
+    iin(9860, 9860, CardScheme::Humo, &[16]),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CardInfo {
+    pub scheme: CardScheme,
+    /// The leading six digits of the number (or fewer, if the number is shorter than that).
+    pub iin: String,
+    /// Number lengths the scheme issues cards with.
+    pub valid_lengths: &'static [usize],
+    /// Whether the length of the given number is one of `valid_lengths`.
+    pub length_valid: bool,
+}
+
+/// Identifies the card scheme of a number from its leading digits. Spaces are ignored. Numbers
+/// that don't match any range in the IIN table are reported as `CardScheme::Unknown`.
+pub fn identify_card(number: &str) -> CardInfo {
+    let digits: String = number.chars().filter(|c| *c != ' ').collect();
+
+    let range = IIN_RANGES
+        .iter()
+        .filter(|range| {
+            let prefix_len = range.low.to_string().len();
+            match digits.get(..prefix_len).map(str::parse::<u32>) {
+                Some(Ok(prefix)) => prefix >= range.low && prefix <= range.high,
+                _ => false,
+            }
+        })
+        .max_by_key(|range| range.low.to_string().len());
+
+    let (scheme, valid_lengths) = match range {
+        Some(range) => (range.scheme, range.lengths),
+        None => (CardScheme::Unknown, UNKNOWN_SCHEME_LENGTHS),
+    };
+
+    CardInfo {
+        scheme,
+        iin: digits.chars().take(6).collect(),
+        valid_lengths,
+        length_valid: valid_lengths.contains(&digits.len()),
+    }
+}
+
 fn m_mul(a: u32, b: u32, modulo: u32) -> u32 {
     let sum = a * b;
     if sum > modulo {
@@ -13,14 +192,32 @@ fn m_mul(a: u32, b: u32, modulo: u32) -> u32 {
     }
 }
 
+/// Computes the Luhn sum of a full number, check digit included. Every second digit is doubled,
+/// counting from the rightmost, so numbers of any length are handled.
+pub fn luhn_sum(digits: &[u32]) -> u32 {
+    digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { m_mul(2, d, 9) } else { d })
+        .sum()
+}
+
+/// Computes the Luhn check digit that should be appended to `payload`.
+pub fn luhn_check_digit(payload: &[u32]) -> u32 {
+    let mut digits = payload.to_vec();
+    digits.push(0);
+    (10 - luhn_sum(&digits) % 10) % 10
+}
+
 pub fn verify_credit_card(credit_card_number: &str) -> Result<(), CreditCardVerificationError> {
     let stripped_input = credit_card_number.replace(" ", "");
 
-    if !stripped_input.chars().all(char::is_numeric) {
+    if !stripped_input.chars().all(|c| c.is_ascii_digit()) {
         return Err(CreditCardVerificationError::InvalidDigitsFound);
     }
 
-    if stripped_input.len() != 16 {
+    if !identify_card(&stripped_input).length_valid {
         return Err(CreditCardVerificationError::InvalidLength);
     }
 
@@ -29,17 +226,22 @@ pub fn verify_credit_card(credit_card_number: &str) -> Result<(), CreditCardVeri
         .map(|c| c.to_digit(10).unwrap())
         .collect();
 
-    let odd = integers.iter().skip(1).step_by(2).map(|d| *d as u32);
-    let even = integers.iter().step_by(2).map(|&d| m_mul(2, d, 9));
-    let both: u32 = itertools::interleave(even, odd).sum();
-
-    if both % 10 == 0 {
+    if luhn_sum(&integers) % 10 == 0 {
         Ok(())
     } else {
         Err(CreditCardVerificationError::InvalidCreditCard)
     }
 }
 
+/// Lists every valid card number that is one digit substitution or one swap of adjacent digits
+/// away from the given number, for offering "did you mean" choices. Spaces in the input are kept
+/// in place in the suggestions.
+pub fn suggest_corrections(credit_card_number: &str) -> Vec<String> {
+    checkdigit::suggest_corrections(credit_card_number, "0123456789", &[' '], |candidate| {
+        verify_credit_card(candidate).is_ok()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,4 +272,57 @@ mod tests {
 
         assert!(verify_credit_card(&String::from(invalid_no)).is_err());
     }
+
+    #[test]
+    pub fn card_identification() {
+        let cards = [
+            ("4111111111111111", CardScheme::Visa),
+            ("4917610000000000", CardScheme::VisaElectron),
+            ("5555555555554444", CardScheme::Mastercard),
+            ("2223003122003222", CardScheme::Mastercard),
+            ("378282246310005", CardScheme::AmericanExpress),
+            ("6011111111111117", CardScheme::Discover),
+            ("6221260000000000", CardScheme::Discover),
+            ("6200000000000005", CardScheme::UnionPay),
+            ("3530111333300000", CardScheme::Jcb),
+            ("6759649826438453", CardScheme::Maestro),
+            ("30569309025904", CardScheme::DinersClub),
+            ("2200000000000004", CardScheme::Mir),
+            ("0980108875738176", CardScheme::Unknown),
+        ];
+
+        for (number, scheme) in cards.iter() {
+            let info = identify_card(number);
+            assert_eq!(info.scheme, *scheme, "{}", number);
+            assert!(info.length_valid, "{}", number);
+        }
+    }
+
+    #[test]
+    pub fn credit_verification_scheme_lengths() {
+        assert!(verify_credit_card("378282246310005").is_ok());
+        assert!(verify_credit_card("4222222222222").is_ok());
+        assert_eq!(
+            verify_credit_card("3782822463100050"),
+            Err(CreditCardVerificationError::InvalidLength)
+        );
+        assert_eq!(
+            verify_credit_card("41111111111111111"),
+            Err(CreditCardVerificationError::InvalidLength)
+        );
+    }
+
+    #[test]
+    pub fn credit_correction_suggestions() {
+        // "4111111111111111" with the fourth digit mistyped
+        let suggestions = suggest_corrections("4112 1111 1111 1111");
+        assert!(suggestions.contains(&String::from("4111 1111 1111 1111")));
+        assert!(suggestions.iter().all(|s| verify_credit_card(s).is_ok()));
+
+        // "4539148803436467" with the last two digits transposed
+        let suggestions = suggest_corrections("4539148803436476");
+        assert!(suggestions.contains(&String::from("4539148803436467")));
+
+        assert!(suggest_corrections("4111 1111 1111 111A").contains(&String::from("4111 1111 1111 1111")));
+    }
 }
@@ -0,0 +1,296 @@
+use super::credit::{luhn_sum, verify_credit_card, CreditCardVerificationError};
+
+use aes::cipher::generic_array::GenericArray;
+use aes::{Aes128, Aes192, Aes256, BlockCipher, NewBlockCipher};
+use std::fmt;
+
+/// Digits of a card number that are kept in the clear at either end of a token.
+const KEPT_PREFIX: usize = 6;
+const KEPT_SUFFIX: usize = 4;
+
+/// SP 800-38G Rev. 1 requires a domain of at least a million values, which is six decimal digits.
+/// The upper bound keeps every intermediate value within a `u128`.
+const MIN_FF1_DIGITS: usize = 6;
+const MAX_FF1_DIGITS: usize = 38;
+
+const RADIX: u128 = 10;
+const ROUNDS: u8 = 10;
+
+#[derive(Debug)]
+pub enum TokenisationError {
+    InvalidKeyLength,
+    InvalidDigitsFound,
+    InvalidDigitCount(usize),
+    InvalidCreditCard(CreditCardVerificationError),
+}
+
+impl fmt::Display for TokenisationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+enum Aes {
+    Aes128(Aes128),
+    Aes192(Aes192),
+    Aes256(Aes256),
+}
+
+/// NIST SP 800-38G FF1 format-preserving encryption over decimal digit strings.
+pub struct Ff1 {
+    cipher: Aes,
+}
+
+impl Ff1 {
+    /// Creates an FF1 instance from a 16, 24 or 32 byte AES key.
+    pub fn new(key: &[u8]) -> Result<Self, TokenisationError> {
+        let cipher = match key.len() {
+            16 => Aes::Aes128(Aes128::new(GenericArray::from_slice(key))),
+            24 => Aes::Aes192(Aes192::new(GenericArray::from_slice(key))),
+            32 => Aes::Aes256(Aes256::new(GenericArray::from_slice(key))),
+            _ => return Err(TokenisationError::InvalidKeyLength),
+        };
+        Ok(Self { cipher })
+    }
+
+    fn ciph(&self, block: &mut [u8; 16]) {
+        let block = GenericArray::from_mut_slice(block);
+        match &self.cipher {
+            Aes::Aes128(cipher) => cipher.encrypt_block(block),
+            Aes::Aes192(cipher) => cipher.encrypt_block(block),
+            Aes::Aes256(cipher) => cipher.encrypt_block(block),
+        }
+    }
+
+    /// CBC-MAC with a zero IV, as used by FF1 as its pseudorandom function.
+    fn prf(&self, input: &[u8]) -> [u8; 16] {
+        let mut y = [0u8; 16];
+        for chunk in input.chunks(16) {
+            for (y, x) in y.iter_mut().zip(chunk.iter()) {
+                *y ^= x;
+            }
+            self.ciph(&mut y);
+        }
+        y
+    }
+
+    /// Computes the round value `y` that FF1 adds to (or subtracts from) one half of the input.
+    fn round(&self, n: usize, tweak: &[u8], round: u8, half: u128) -> u128 {
+        let u = n / 2;
+        let v = n - u;
+        let b = (bit_length(RADIX.pow(v as u32)) + 7) / 8;
+        let d = 4 * ((b + 3) / 4) + 4;
+
+        let mut p = vec![1, 2, 1, 0, 0, RADIX as u8, 10, u as u8];
+        p.extend_from_slice(&(n as u32).to_be_bytes());
+        p.extend_from_slice(&(tweak.len() as u32).to_be_bytes());
+
+        let mut q = tweak.to_vec();
+        q.resize(q.len() + (16 - (tweak.len() + b + 1) % 16) % 16, 0);
+        q.push(round);
+        q.extend_from_slice(&half.to_be_bytes()[16 - b..]);
+
+        p.extend(q);
+        let r = self.prf(&p);
+
+        // With at most 19 digits in a half d is never more than 12, so S is just the start of R.
+        r[..d].iter().fold(0u128, |acc, &byte| (acc << 8) | byte as u128)
+    }
+
+    pub fn encrypt(&self, digits: &[u32], tweak: &[u8]) -> Result<Vec<u32>, TokenisationError> {
+        let n = check_ff1_digits(digits)?;
+        let u = n / 2;
+        let (mut a, mut b) = (to_num(&digits[..u]), to_num(&digits[u..]));
+
+        for i in 0..ROUNDS {
+            let m = if i % 2 == 0 { u } else { n - u };
+            let c = (a + self.round(n, tweak, i, b) % RADIX.pow(m as u32)) % RADIX.pow(m as u32);
+            a = b;
+            b = c;
+        }
+
+        Ok(join_halves(a, u, b, n - u))
+    }
+
+    pub fn decrypt(&self, digits: &[u32], tweak: &[u8]) -> Result<Vec<u32>, TokenisationError> {
+        let n = check_ff1_digits(digits)?;
+        let u = n / 2;
+        let (mut a, mut b) = (to_num(&digits[..u]), to_num(&digits[u..]));
+
+        for i in (0..ROUNDS).rev() {
+            let m = if i % 2 == 0 { u } else { n - u };
+            let modulus = RADIX.pow(m as u32);
+            let c = (b + modulus - self.round(n, tweak, i, a) % modulus) % modulus;
+            b = a;
+            a = c;
+        }
+
+        Ok(join_halves(a, u, b, n - u))
+    }
+}
+
+fn check_ff1_digits(digits: &[u32]) -> Result<usize, TokenisationError> {
+    if digits.iter().any(|&d| d >= RADIX as u32) {
+        return Err(TokenisationError::InvalidDigitsFound);
+    }
+    if digits.len() < MIN_FF1_DIGITS || digits.len() > MAX_FF1_DIGITS {
+        return Err(TokenisationError::InvalidDigitCount(digits.len()));
+    }
+    Ok(digits.len())
+}
+
+fn bit_length(value: u128) -> usize {
+    128 - value.leading_zeros() as usize
+}
+
+fn to_num(digits: &[u32]) -> u128 {
+    digits.iter().fold(0, |acc, &d| acc * RADIX + d as u128)
+}
+
+fn to_digits(mut value: u128, length: usize) -> Vec<u32> {
+    let mut digits = vec![0; length];
+    for digit in digits.iter_mut().rev() {
+        *digit = (value % RADIX) as u32;
+        value /= RADIX;
+    }
+    digits
+}
+
+fn join_halves(a: u128, a_length: usize, b: u128, b_length: usize) -> Vec<u32> {
+    let mut digits = to_digits(a, a_length);
+    digits.extend(to_digits(b, b_length));
+    digits
+}
+
+/// Replaces card numbers with tokens of the same length that keep the IIN and the last four
+/// digits, and still pass `verify_credit_card`.
+///
+/// Because a valid number's digits are tied together by the Luhn check, the last digit before the
+/// kept suffix is not encrypted but recomputed so the token passes the check; the digits between
+/// the IIN and that digit are encrypted with FF1, tweaked with the kept digits. FF1 needs at least
+/// six of them, so only numbers of 17 digits or more can be tokenised; 16 digit numbers would
+/// leave five, and are rejected.
+pub struct CardTokeniser {
+    ff1: Ff1,
+}
+
+impl CardTokeniser {
+    pub fn new(key: &[u8]) -> Result<Self, TokenisationError> {
+        Ok(Self { ff1: Ff1::new(key)? })
+    }
+
+    pub fn tokenise(&self, credit_card_number: &str) -> Result<String, TokenisationError> {
+        self.transform(credit_card_number, |digits, tweak| self.ff1.encrypt(digits, tweak))
+    }
+
+    pub fn detokenise(&self, token: &str) -> Result<String, TokenisationError> {
+        self.transform(token, |digits, tweak| self.ff1.decrypt(digits, tweak))
+    }
+
+    fn transform<F>(&self, number: &str, cipher: F) -> Result<String, TokenisationError>
+    where
+        F: Fn(&[u32], &[u8]) -> Result<Vec<u32>, TokenisationError>,
+    {
+        verify_credit_card(number).map_err(TokenisationError::InvalidCreditCard)?;
+
+        let mut digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+        let n = digits.len();
+        if n < KEPT_PREFIX + MIN_FF1_DIGITS + 1 + KEPT_SUFFIX {
+            return Err(TokenisationError::InvalidDigitCount(n));
+        }
+
+        let luhn_position = n - KEPT_SUFFIX - 1;
+        let tweak: Vec<u8> = digits[..KEPT_PREFIX]
+            .iter()
+            .chain(digits[n - KEPT_SUFFIX..].iter())
+            .map(|&d| b'0' + d as u8)
+            .collect();
+
+        let transformed = cipher(&digits[KEPT_PREFIX..luhn_position], &tweak)?;
+        digits.splice(KEPT_PREFIX..luhn_position, transformed);
+
+        digits[luhn_position] = (0..10)
+            .find(|&d| {
+                let mut candidate = digits.clone();
+                candidate[luhn_position] = d;
+                luhn_sum(&candidate) % 10 == 0
+            })
+            .unwrap();
+
+        Ok(digits.iter().map(|&d| std::char::from_digit(d, 10).unwrap()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(input: &str) -> Vec<u32> {
+        input.chars().map(|c| c.to_digit(10).unwrap()).collect()
+    }
+
+    #[test]
+    pub fn ff1_nist_samples() {
+        let samples = [
+            ("2B7E151628AED2A6ABF7158809CF4F3C", "", "2433477484"),
+            ("2B7E151628AED2A6ABF7158809CF4F3C", "39383736353433323130", "6124200773"),
+            ("2B7E151628AED2A6ABF7158809CF4F3CEF4359D8D580AA4F", "", "2830668132"),
+            (
+                "2B7E151628AED2A6ABF7158809CF4F3CEF4359D8D580AA4F7F036D6F04FC6A94",
+                "",
+                "6657667009",
+            ),
+            (
+                "2B7E151628AED2A6ABF7158809CF4F3CEF4359D8D580AA4F7F036D6F04FC6A94",
+                "39383736353433323130",
+                "1001623463",
+            ),
+        ];
+
+        for (key, tweak, ciphertext) in samples.iter() {
+            let ff1 = Ff1::new(&hex::decode(key).unwrap()).unwrap();
+            let tweak = hex::decode(tweak).unwrap();
+            let encrypted = ff1.encrypt(&digits("0123456789"), &tweak).unwrap();
+            assert_eq!(encrypted, digits(ciphertext));
+            assert_eq!(ff1.decrypt(&encrypted, &tweak).unwrap(), digits("0123456789"));
+        }
+    }
+
+    #[test]
+    pub fn card_tokenisation_round_trip() {
+        let tokeniser = CardTokeniser::new(b"0123456789abcdef").unwrap();
+        let numbers = ["60111111111111113", "622126123456789015", "4111 1111 1111 1111 110"];
+
+        for number in numbers.iter() {
+            let token = tokeniser.tokenise(number).unwrap();
+            let bare: String = number.chars().filter(|c| *c != ' ').collect();
+            assert_ne!(token, bare);
+            assert_eq!(token.len(), bare.len());
+            assert_eq!(token[..6], bare[..6]);
+            assert_eq!(token[token.len() - 4..], bare[bare.len() - 4..]);
+            assert!(verify_credit_card(&token).is_ok(), "{}", token);
+            assert_eq!(tokeniser.detokenise(&token).unwrap(), bare);
+        }
+    }
+
+    #[test]
+    pub fn card_tokenisation_errors() {
+        assert!(CardTokeniser::new(b"short").is_err());
+
+        let tokeniser = CardTokeniser::new(&[7u8; 32]).unwrap();
+        assert!(tokeniser.tokenise("4111111111111112").is_err());
+        assert!(tokeniser.tokenise("41111111111A1111").is_err());
+
+        // Too few digits between the kept ends for FF1's minimum domain
+        for number in ["4111111111111111", "378282246310005", "6011 1111 1111 1117"].iter() {
+            assert!(matches!(
+                tokeniser.tokenise(number),
+                Err(TokenisationError::InvalidDigitCount(_))
+            ));
+        }
+        assert!(matches!(
+            Ff1::new(&[7u8; 16]).unwrap().encrypt(&digits("12345"), b""),
+            Err(TokenisationError::InvalidDigitCount(5))
+        ));
+    }
+}
@@ -1,4 +1,13 @@
+use serde::{Deserialize, Serialize};
 use sha1::Sha1;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Size of the chunks streamed into the hasher by `hash_reader`.
+const CHUNK_SIZE: usize = 64 * 1024;
 
 pub fn sha1(input: &str) -> String {
     let mut hasher = Sha1::new();
@@ -6,6 +15,310 @@ pub fn sha1(input: &str) -> String {
     hasher.digest().to_string()
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    MD5,
+    SHA1,
+    SHA224,
+    SHA256,
+    SHA384,
+    SHA512,
+    SHA3_256,
+    BLAKE2b,
+    BLAKE3,
+}
+
+pub const ALGORITHMS: [HashAlgorithm; 9] = [
+    HashAlgorithm::MD5,
+    HashAlgorithm::SHA1,
+    HashAlgorithm::SHA224,
+    HashAlgorithm::SHA256,
+    HashAlgorithm::SHA384,
+    HashAlgorithm::SHA512,
+    HashAlgorithm::SHA3_256,
+    HashAlgorithm::BLAKE2b,
+    HashAlgorithm::BLAKE3,
+];
+
+/// An incremental hash function. `finish` returns the digest of everything passed to `update`
+/// and resets the state, so one instance can hash many messages.
+pub trait Digest {
+    fn update(&mut self, data: &[u8]);
+    fn finish(&mut self) -> Vec<u8>;
+    fn reset(&mut self);
+    /// The length of the digest in bytes.
+    fn output_len(&self) -> usize;
+    /// The size of the blocks the compression function works on, in bytes.
+    fn block_len(&self) -> usize;
+}
+
+struct Sha1Digest(Sha1);
+
+impl Digest for Sha1Digest {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let digest = self.0.digest().bytes().to_vec();
+        self.0.reset();
+        digest
+    }
+
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    fn output_len(&self) -> usize {
+        20
+    }
+
+    fn block_len(&self) -> usize {
+        64
+    }
+}
+
+/// Adapts the RustCrypto hashes, which all share the `digest` crate's trait.
+struct RustCryptoDigest<D> {
+    hasher: D,
+    block_len: usize,
+}
+
+impl<D: sha2::Digest> Digest for RustCryptoDigest<D> {
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(&mut self.hasher, data);
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        self.hasher.finalize_reset().to_vec()
+    }
+
+    fn reset(&mut self) {
+        sha2::Digest::reset(&mut self.hasher);
+    }
+
+    fn output_len(&self) -> usize {
+        D::output_size()
+    }
+
+    fn block_len(&self) -> usize {
+        self.block_len
+    }
+}
+
+struct Blake3Digest(blake3::Hasher);
+
+impl Digest for Blake3Digest {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let digest = self.0.finalize().as_bytes().to_vec();
+        self.0.reset();
+        digest
+    }
+
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    fn output_len(&self) -> usize {
+        blake3::OUT_LEN
+    }
+
+    fn block_len(&self) -> usize {
+        blake3::BLOCK_LEN
+    }
+}
+
+impl HashAlgorithm {
+    pub fn hasher(self) -> Box<dyn Digest + Send> {
+        fn rust_crypto<D: sha2::Digest + Send + 'static>(block_len: usize) -> Box<dyn Digest + Send> {
+            Box::new(RustCryptoDigest {
+                hasher: D::new(),
+                block_len,
+            })
+        }
+
+        match self {
+            HashAlgorithm::MD5 => rust_crypto::<md5::Md5>(64),
+            HashAlgorithm::SHA1 => Box::new(Sha1Digest(Sha1::new())),
+            HashAlgorithm::SHA224 => rust_crypto::<sha2::Sha224>(64),
+            HashAlgorithm::SHA256 => rust_crypto::<sha2::Sha256>(64),
+            HashAlgorithm::SHA384 => rust_crypto::<sha2::Sha384>(128),
+            HashAlgorithm::SHA512 => rust_crypto::<sha2::Sha512>(128),
+            HashAlgorithm::SHA3_256 => rust_crypto::<sha3::Sha3_256>(136),
+            HashAlgorithm::BLAKE2b => rust_crypto::<blake2::Blake2b>(128),
+            HashAlgorithm::BLAKE3 => Box::new(Blake3Digest(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    pub fn hex_digest(self, data: &[u8]) -> String {
+        hex::encode(self.digest(data))
+    }
+
+    /// The length of the digest in bytes.
+    pub fn output_len(self) -> usize {
+        self.hasher().output_len()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::MD5 => "md5",
+            HashAlgorithm::SHA1 => "sha1",
+            HashAlgorithm::SHA224 => "sha224",
+            HashAlgorithm::SHA256 => "sha256",
+            HashAlgorithm::SHA384 => "sha384",
+            HashAlgorithm::SHA512 => "sha512",
+            HashAlgorithm::SHA3_256 => "sha3-256",
+            HashAlgorithm::BLAKE2b => "blake2b",
+            HashAlgorithm::BLAKE3 => "blake3",
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownHashAlgorithm(pub String);
+
+impl fmt::Display for UnknownHashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown hash algorithm '{}'", self.0)
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = UnknownHashAlgorithm;
+
+    /// Parses an algorithm name such as `sha256` or `SHA-256`, ignoring case and hyphens.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalised = s.to_ascii_lowercase().replace("-", "").replace("_", "");
+        ALGORITHMS
+            .iter()
+            .find(|algorithm| algorithm.name().replace("-", "") == normalised)
+            .copied()
+            .ok_or_else(|| UnknownHashAlgorithm(String::from(s)))
+    }
+}
+
+/// Hashes everything `reader` produces, a chunk at a time, so input of any size can be hashed
+/// without holding it in memory.
+pub fn hash_reader<R: Read>(algorithm: HashAlgorithm, mut reader: R) -> io::Result<Vec<u8>> {
+    let mut hasher = algorithm.hasher();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finish()),
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+pub fn hash_file<P: AsRef<Path>>(algorithm: HashAlgorithm, path: P) -> io::Result<Vec<u8>> {
+    hash_reader(algorithm, File::open(path)?)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum ChecksumStatus {
+    Ok,
+    Failed,
+    /// The file couldn't be opened or read.
+    Unreadable,
+    /// The line isn't of the form `<hex digest>  <path>`.
+    Malformed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChecksumResult {
+    pub line: usize,
+    pub path: PathBuf,
+    pub status: ChecksumStatus,
+}
+
+impl fmt::Display for ChecksumResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            ChecksumStatus::Ok => write!(f, "{}: OK", self.path.display()),
+            ChecksumStatus::Failed => write!(f, "{}: FAILED", self.path.display()),
+            ChecksumStatus::Unreadable => write!(f, "{}: FAILED open or read", self.path.display()),
+            ChecksumStatus::Malformed => write!(f, "line {}: improperly formatted checksum line", self.line),
+        }
+    }
+}
+
+/// Splits a `sha1sum`-style line into its digest and path. The path is separated from the digest
+/// by a space and then either a space (text mode) or `*` (binary mode), which are equivalent here.
+fn parse_checksum_line(line: &str, output_len: usize) -> Option<(Vec<u8>, &str)> {
+    let digest = line.get(..output_len * 2)?;
+    let path = line.get(output_len * 2..)?;
+    let path = path.strip_prefix(" *").or_else(|| path.strip_prefix("  "))?;
+    if path.is_empty() {
+        return None;
+    }
+    Some((hex::decode(digest).ok()?, path))
+}
+
+/// Checks each file listed in `sha1sum`/`sha256sum` format against its expected digest. Relative
+/// paths are resolved against `base`. Blank lines and `#` comments are skipped.
+pub fn verify_checksums<R: BufRead>(
+    algorithm: HashAlgorithm,
+    checksums: R,
+    base: &Path,
+) -> io::Result<Vec<ChecksumResult>> {
+    let output_len = algorithm.output_len();
+    let mut results = Vec::new();
+
+    for (index, line) in checksums.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (status, path) = match parse_checksum_line(line, output_len) {
+            None => (ChecksumStatus::Malformed, PathBuf::new()),
+            Some((expected, path)) => {
+                let path = PathBuf::from(path);
+                let status = match hash_file(algorithm, base.join(&path)) {
+                    Ok(digest) if digest == expected => ChecksumStatus::Ok,
+                    Ok(_) => ChecksumStatus::Failed,
+                    Err(_) => ChecksumStatus::Unreadable,
+                };
+                (status, path)
+            }
+        };
+        results.push(ChecksumResult {
+            line: index + 1,
+            path,
+            status,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Verifies a checksum file such as `SHA256SUMS`, resolving the paths it lists relative to the
+/// directory the checksum file is in.
+pub fn verify_checksum_file<P: AsRef<Path>>(algorithm: HashAlgorithm, path: P) -> io::Result<Vec<ChecksumResult>> {
+    let path = path.as_ref();
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    verify_checksums(algorithm, BufReader::new(File::open(path)?), base)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -15,4 +328,123 @@ mod tests {
         let result = sha1(input);
         assert_eq!(result, "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d");
     }
+
+    #[test]
+    fn known_answers() {
+        let answers = [
+            (HashAlgorithm::MD5, "900150983cd24fb0d6963f7d28e17f72"),
+            (HashAlgorithm::SHA1, "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                HashAlgorithm::SHA224,
+                "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+            ),
+            (
+                HashAlgorithm::SHA256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                HashAlgorithm::SHA384,
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed\
+                8086072ba1e7cc2358baeca134c825a7",
+            ),
+            (
+                HashAlgorithm::SHA512,
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+            (
+                HashAlgorithm::SHA3_256,
+                "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            ),
+            (
+                HashAlgorithm::BLAKE2b,
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+                7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+            ),
+            (
+                HashAlgorithm::BLAKE3,
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+        ];
+
+        for (algorithm, answer) in answers.iter() {
+            assert_eq!(algorithm.hex_digest(b"abc"), *answer, "{}", algorithm);
+            assert_eq!(algorithm.digest(b"abc").len(), algorithm.output_len());
+        }
+    }
+
+    #[test]
+    fn incremental_hashing() {
+        for &algorithm in ALGORITHMS.iter() {
+            let mut hasher = algorithm.hasher();
+            hasher.update(b"a");
+            hasher.update(b"bc");
+            assert_eq!(hasher.finish(), algorithm.digest(b"abc"), "{}", algorithm);
+
+            // finish resets, so the hasher can be reused
+            hasher.update(b"abc");
+            assert_eq!(hasher.finish(), algorithm.digest(b"abc"), "{}", algorithm);
+        }
+    }
+
+    #[test]
+    fn algorithm_names() {
+        assert_eq!("SHA-256".parse(), Ok(HashAlgorithm::SHA256));
+        assert_eq!("sha3-256".parse(), Ok(HashAlgorithm::SHA3_256));
+        assert_eq!("md5".parse(), Ok(HashAlgorithm::MD5));
+        assert!("sha0".parse::<HashAlgorithm>().is_err());
+        for &algorithm in ALGORITHMS.iter() {
+            assert_eq!(algorithm.name().parse(), Ok(algorithm));
+        }
+    }
+
+    #[test]
+    fn streaming_hashes() {
+        // longer than a chunk, so the digest spans several reads
+        let input: Vec<u8> = (0..CHUNK_SIZE * 2 + 17).map(|i| i as u8).collect();
+        for &algorithm in ALGORITHMS.iter() {
+            assert_eq!(
+                hash_reader(algorithm, input.as_slice()).unwrap(),
+                algorithm.digest(&input),
+                "{}",
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn checksum_file_verification() {
+        let dir = std::env::temp_dir().join(format!("crypto-checksums-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "abc").unwrap();
+        std::fs::write(dir.join("b.txt"), "abd").unwrap();
+        assert_eq!(
+            hash_file(HashAlgorithm::SHA1, dir.join("a.txt")).unwrap(),
+            HashAlgorithm::SHA1.digest(b"abc")
+        );
+
+        let abc = HashAlgorithm::SHA256.hex_digest(b"abc");
+        let checksums = format!(
+            "{0}  a.txt\n{0} *b.txt\n\n# comment\n{0}  missing.txt\nnot a checksum\n",
+            abc
+        );
+        std::fs::write(dir.join("SHA256SUMS"), checksums).unwrap();
+
+        let results = verify_checksum_file(HashAlgorithm::SHA256, dir.join("SHA256SUMS")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let statuses: Vec<ChecksumStatus> = results.iter().map(|result| result.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ChecksumStatus::Ok,
+                ChecksumStatus::Failed,
+                ChecksumStatus::Unreadable,
+                ChecksumStatus::Malformed
+            ]
+        );
+        assert_eq!(results[0].to_string(), "a.txt: OK");
+        assert_eq!(results[1].to_string(), "b.txt: FAILED");
+        assert_eq!(results[3].line, 6);
+    }
 }
@@ -0,0 +1,187 @@
+use super::hash::HashAlgorithm;
+use super::hmac::Hmac;
+
+use std::fmt;
+
+/// RFC 5869 caps the output at 255 blocks, as the block counter is a single byte.
+const MAX_BLOCKS: usize = 255;
+
+#[derive(Debug, PartialEq)]
+pub enum HkdfError {
+    /// The pseudorandom key is shorter than the hash output.
+    InvalidPrkLength(usize),
+    /// More output was requested than HKDF can produce with this hash.
+    OutputTooLong(usize),
+}
+
+impl fmt::Display for HkdfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Incremental HKDF-Extract, for input keying material that arrives in pieces.
+pub struct HkdfExtract {
+    hmac: Hmac,
+}
+
+impl HkdfExtract {
+    /// An empty salt is replaced by a string of zeros as long as the hash output, as RFC 5869 says.
+    pub fn new(algorithm: HashAlgorithm, salt: &[u8]) -> Self {
+        let hmac = if salt.is_empty() {
+            Hmac::new(algorithm, &vec![0; algorithm.output_len()])
+        } else {
+            Hmac::new(algorithm, salt)
+        };
+        Self { hmac }
+    }
+
+    pub fn update(&mut self, ikm: &[u8]) {
+        self.hmac.update(ikm);
+    }
+
+    pub fn finish(mut self) -> Hkdf {
+        Hkdf {
+            algorithm: self.hmac.algorithm(),
+            prk: self.hmac.finish(),
+        }
+    }
+}
+
+/// HKDF (RFC 5869) holding an extracted pseudorandom key, from which any number of keys can be
+/// expanded with different `info` strings.
+pub struct Hkdf {
+    algorithm: HashAlgorithm,
+    prk: Vec<u8>,
+}
+
+impl Hkdf {
+    pub fn extract(algorithm: HashAlgorithm, salt: &[u8], ikm: &[u8]) -> Self {
+        let mut extract = HkdfExtract::new(algorithm, salt);
+        extract.update(ikm);
+        extract.finish()
+    }
+
+    /// Skips the extract step, for keys that are already uniformly random.
+    pub fn from_prk(algorithm: HashAlgorithm, prk: &[u8]) -> Result<Self, HkdfError> {
+        if prk.len() < algorithm.output_len() {
+            return Err(HkdfError::InvalidPrkLength(prk.len()));
+        }
+        Ok(Self {
+            algorithm,
+            prk: prk.to_vec(),
+        })
+    }
+
+    pub fn prk(&self) -> &[u8] {
+        &self.prk
+    }
+
+    pub fn expand(&self, info: &[u8], length: usize) -> Result<Vec<u8>, HkdfError> {
+        let output_len = self.algorithm.output_len();
+        if length > MAX_BLOCKS * output_len {
+            return Err(HkdfError::OutputTooLong(length));
+        }
+
+        let mut hmac = Hmac::new(self.algorithm, &self.prk);
+        let mut okm = Vec::with_capacity(length);
+        let mut block = Vec::new();
+        let mut counter = 1u8;
+
+        while okm.len() < length {
+            hmac.update(&block);
+            hmac.update(info);
+            hmac.update(&[counter]);
+            block = hmac.finish();
+            okm.extend_from_slice(&block[..output_len.min(length - okm.len())]);
+            counter = counter.wrapping_add(1);
+        }
+
+        Ok(okm)
+    }
+}
+
+/// One-shot HKDF: extracts a pseudorandom key from `ikm` and expands it to `length` bytes.
+pub fn hkdf(
+    algorithm: HashAlgorithm,
+    salt: &[u8],
+    ikm: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, HkdfError> {
+    Hkdf::extract(algorithm, salt, ikm).expand(info, length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn rfc5869_test_vectors() {
+        let vectors = [
+            (
+                HashAlgorithm::SHA256,
+                vec![0x0b; 22],
+                "000102030405060708090a0b0c",
+                "f0f1f2f3f4f5f6f7f8f9",
+                "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+            ),
+            (
+                HashAlgorithm::SHA256,
+                vec![0x0b; 22],
+                "",
+                "",
+                "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8",
+            ),
+            (
+                HashAlgorithm::SHA1,
+                vec![0x0b; 11],
+                "000102030405060708090a0b0c",
+                "f0f1f2f3f4f5f6f7f8f9",
+                "9b6c18c432a7bf8f0e71c8eb88f4b30baa2ba243",
+                "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896",
+            ),
+        ];
+
+        for (algorithm, ikm, salt, info, prk, okm) in vectors.iter() {
+            let salt = hex::decode(salt).unwrap();
+            let info = hex::decode(info).unwrap();
+            let hkdf_instance = Hkdf::extract(*algorithm, &salt, ikm);
+            assert_eq!(hex::encode(hkdf_instance.prk()), *prk);
+            assert_eq!(hex::encode(hkdf_instance.expand(&info, 42).unwrap()), *okm);
+            assert_eq!(hex::encode(hkdf(*algorithm, &salt, ikm, &info, 42).unwrap()), *okm);
+        }
+    }
+
+    #[test]
+    pub fn incremental_extract() {
+        let mut extract = HkdfExtract::new(HashAlgorithm::SHA256, b"salt");
+        extract.update(b"input ");
+        extract.update(b"keying material");
+        let incremental = extract.finish();
+        let one_shot = Hkdf::extract(HashAlgorithm::SHA256, b"salt", b"input keying material");
+        assert_eq!(incremental.prk(), one_shot.prk());
+
+        let prk = Hkdf::from_prk(HashAlgorithm::SHA256, one_shot.prk()).unwrap();
+        assert_eq!(
+            prk.expand(b"info", 100).unwrap(),
+            one_shot.expand(b"info", 100).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn hkdf_errors() {
+        assert_eq!(
+            Hkdf::from_prk(HashAlgorithm::SHA256, &[0; 16]).err(),
+            Some(HkdfError::InvalidPrkLength(16))
+        );
+        let hkdf_instance = Hkdf::extract(HashAlgorithm::SHA1, b"", b"ikm");
+        assert!(hkdf_instance.expand(b"", 255 * 20).is_ok());
+        assert_eq!(
+            hkdf_instance.expand(b"", 255 * 20 + 1),
+            Err(HkdfError::OutputTooLong(255 * 20 + 1))
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use super::hash::{Digest, HashAlgorithm};
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+/// HMAC (RFC 2104) over any of the supported hash algorithms.
+///
+/// Data can be fed in with `update` as it arrives; `finish` returns the tag and leaves the
+/// instance ready to authenticate another message with the same key.
+pub struct Hmac {
+    algorithm: HashAlgorithm,
+    inner: Box<dyn Digest + Send>,
+    inner_pad: Vec<u8>,
+    outer_pad: Vec<u8>,
+}
+
+impl Hmac {
+    pub fn new(algorithm: HashAlgorithm, key: &[u8]) -> Self {
+        let mut inner = algorithm.hasher();
+        let block_len = inner.block_len();
+
+        // Keys longer than a block are hashed first, then everything is zero padded to a block
+        let mut key = if key.len() > block_len {
+            algorithm.digest(key)
+        } else {
+            key.to_vec()
+        };
+        key.resize(block_len, 0);
+
+        let inner_pad: Vec<u8> = key.iter().map(|b| b ^ IPAD).collect();
+        let outer_pad: Vec<u8> = key.iter().map(|b| b ^ OPAD).collect();
+        inner.update(&inner_pad);
+
+        Self {
+            algorithm,
+            inner,
+            inner_pad,
+            outer_pad,
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// The length of the tags this instance produces in bytes.
+    pub fn output_len(&self) -> usize {
+        self.inner.output_len()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finish(&mut self) -> Vec<u8> {
+        let inner_hash = self.inner.finish();
+        self.inner.update(&self.inner_pad);
+
+        let mut outer = self.algorithm.hasher();
+        outer.update(&self.outer_pad);
+        outer.update(&inner_hash);
+        outer.finish()
+    }
+
+    /// Checks the message fed in so far against `tag`, taking the same time wherever they differ.
+    pub fn verify(&mut self, tag: &[u8]) -> bool {
+        constant_time_eq(&self.finish(), tag)
+    }
+}
+
+pub fn hmac(algorithm: HashAlgorithm, key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::new(algorithm, key);
+    mac.update(message);
+    mac.finish()
+}
+
+pub fn verify_hmac(algorithm: HashAlgorithm, key: &[u8], message: &[u8], tag: &[u8]) -> bool {
+    constant_time_eq(&hmac(algorithm, key, message), tag)
+}
+
+/// Compares two byte strings without returning early at the first difference, so the time taken
+/// doesn't reveal how much of a guessed tag was right. Only the lengths are compared directly.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::ALGORITHMS;
+
+    #[test]
+    pub fn rfc_test_vectors() {
+        // RFC 2104 for MD5, RFC 2202 for SHA-1 and RFC 4231 for SHA-2
+        let vectors = [
+            (
+                HashAlgorithm::MD5,
+                vec![0x0b; 16],
+                "Hi There",
+                "9294727a3638bb1c13f48ef8158bfc9d",
+            ),
+            (
+                HashAlgorithm::MD5,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "750c783e6ab0b503eaa86e310a5db738",
+            ),
+            (
+                HashAlgorithm::SHA1,
+                vec![0x0b; 20],
+                "Hi There",
+                "b617318655057264e28bc0b6fb378c8ef146be00",
+            ),
+            (
+                HashAlgorithm::SHA1,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            ),
+            (
+                HashAlgorithm::SHA224,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+            ),
+            (
+                HashAlgorithm::SHA256,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                HashAlgorithm::SHA256,
+                vec![0xaa; 131],
+                "Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                HashAlgorithm::SHA384,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e\
+                8e2240ca5e69e2c78b3239ecfab21649",
+            ),
+            (
+                HashAlgorithm::SHA512,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+                9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+            ),
+            (
+                HashAlgorithm::SHA3_256,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "c7d4072e788877ae3596bbb0da73b887c9171f93095b294ae857fbe2645e1ba5",
+            ),
+            (
+                HashAlgorithm::SHA3_256,
+                vec![0xaa; 131],
+                "Test Using Larger Than Block-Size Key - Hash Key First",
+                "ed73a374b96c005235f948032f09674a58c0ce555cfc1f223b02356560312c3b",
+            ),
+            (
+                HashAlgorithm::BLAKE2b,
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                "6ff884f8ddc2a6586b3c98a4cd6ebdf14ec10204b6710073eb5865ade37a2643\
+                b8807c1335d107ecdb9ffeaeb6828c4625ba172c66379efcd222c2de11727ab4",
+            ),
+        ];
+
+        for (algorithm, key, message, tag) in vectors.iter() {
+            assert_eq!(
+                hex::encode(hmac(*algorithm, key, message.as_bytes())),
+                *tag,
+                "{}",
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    pub fn incremental_hmac() {
+        for &algorithm in ALGORITHMS.iter() {
+            let expected = hmac(algorithm, b"key", b"The quick brown fox");
+            let mut mac = Hmac::new(algorithm, b"key");
+            mac.update(b"The quick ");
+            mac.update(b"brown fox");
+            assert_eq!(mac.finish(), expected, "{}", algorithm);
+            assert_eq!(expected.len(), mac.output_len());
+
+            // finish leaves the key in place for the next message
+            mac.update(b"The quick brown fox");
+            assert!(mac.verify(&expected));
+        }
+    }
+
+    #[test]
+    pub fn hmac_verification() {
+        let tag = hmac(HashAlgorithm::SHA256, b"key", b"message");
+        assert!(verify_hmac(HashAlgorithm::SHA256, b"key", b"message", &tag));
+        assert!(!verify_hmac(HashAlgorithm::SHA256, b"key", b"massage", &tag));
+        assert!(!verify_hmac(HashAlgorithm::SHA256, b"kay", b"message", &tag));
+        assert!(!verify_hmac(HashAlgorithm::SHA256, b"key", b"message", &tag[..31]));
+    }
+}
@@ -0,0 +1,368 @@
+use super::checkdigit::{CheckDigitScheme, Iso7064Mod97};
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum IBANVerificationError {
+    InvalidCharactersFound,
+    UnknownCountry,
+    InvalidLength,
+    InvalidBBANFormat,
+    InvalidCheckDigits,
+}
+
+impl fmt::Display for IBANVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IBANVerificationError::InvalidCharactersFound => {
+                write!(f, "IBAN error: Only letters and digits may appear in an IBAN")
+            }
+            IBANVerificationError::UnknownCountry => {
+                write!(f, "IBAN error: The country code is not one that issues IBANs")
+            }
+            IBANVerificationError::InvalidLength => {
+                write!(f, "IBAN error: The IBAN has the wrong length for its country")
+            }
+            IBANVerificationError::InvalidBBANFormat => {
+                write!(f, "IBAN error: The account number doesn't match the format used by its country")
+            }
+            IBANVerificationError::InvalidCheckDigits => {
+                write!(f, "IBAN error: The check digits are not correct")
+            }
+        }
+    }
+}
+
+/// IBAN length and BBAN structure for each country, from the SWIFT IBAN registry. Structures use
+/// the registry's notation: `4!n` is exactly four digits, `4!a` four upper case letters and `4!c`
+/// four upper case letters or digits.
+const COUNTRIES: [(&str, usize, &str); 79] = [
+    ("AD", 24, "4!n4!n12!c"),
+    ("AE", 23, "3!n16!n"),
+    ("AL", 28, "8!n16!c"),
+    ("AT", 20, "5!n11!n"),
+    ("AZ", 28, "4!a20!c"),
+    ("BA", 20, "3!n3!n8!n2!n"),
+    ("BE", 16, "3!n7!n2!n"),
+    ("BG", 22, "4!a4!n2!n8!c"),
+    ("BH", 22, "4!a14!c"),
+    ("BR", 29, "8!n5!n10!n1!a1!c"),
+    ("BY", 28, "4!c4!n16!c"),
+    ("CH", 21, "5!n12!c"),
+    ("CR", 22, "4!n14!n"),
+    ("CY", 28, "3!n5!n16!c"),
+    ("CZ", 24, "4!n6!n10!n"),
+    ("DE", 22, "8!n10!n"),
+    ("DK", 18, "4!n9!n1!n"),
+    ("DO", 28, "4!c20!n"),
+    ("EE", 20, "2!n2!n11!n1!n"),
+    ("EG", 29, "4!n4!n17!n"),
+    ("ES", 24, "4!n4!n1!n1!n10!n"),
+    ("FI", 18, "3!n11!n"),
+    ("FO", 18, "4!n9!n1!n"),
+    ("FR", 27, "5!n5!n11!c2!n"),
+    ("GB", 22, "4!a6!n8!n"),
+    ("GE", 22, "2!a16!n"),
+    ("GI", 23, "4!a15!c"),
+    ("GL", 18, "4!n9!n1!n"),
+    ("GR", 27, "3!n4!n16!c"),
+    ("GT", 28, "4!c20!c"),
+    ("HR", 21, "7!n10!n"),
+    ("HU", 28, "3!n4!n1!n15!n1!n"),
+    ("IE", 22, "4!a6!n8!n"),
+    ("IL", 23, "3!n3!n13!n"),
+    ("IQ", 23, "4!a3!n12!n"),
+    ("IS", 26, "4!n2!n6!n10!n"),
+    ("IT", 27, "1!a5!n5!n12!c"),
+    ("JO", 30, "4!a4!n18!c"),
+    ("KW", 30, "4!a22!c"),
+    ("KZ", 20, "3!n13!c"),
+    ("LB", 28, "4!n20!c"),
+    ("LC", 32, "4!a24!c"),
+    ("LI", 21, "5!n12!c"),
+    ("LT", 20, "5!n11!n"),
+    ("LU", 20, "3!n13!c"),
+    ("LV", 21, "4!a13!c"),
+    ("LY", 25, "3!n3!n15!n"),
+    ("MC", 27, "5!n5!n11!c2!n"),
+    ("MD", 24, "2!c18!c"),
+    ("ME", 22, "3!n13!n2!n"),
+    ("MK", 19, "3!n10!c2!n"),
+    ("MR", 27, "5!n5!n11!n2!n"),
+    ("MT", 31, "4!a5!n18!c"),
+    ("MU", 30, "4!a2!n2!n12!n3!n3!a"),
+    ("NL", 18, "4!a10!n"),
+    ("NO", 15, "4!n6!n1!n"),
+    ("PK", 24, "4!a16!c"),
+    ("PL", 28, "8!n16!n"),
+    ("PS", 29, "4!a21!c"),
+    ("PT", 25, "4!n4!n11!n2!n"),
+    ("QA", 29, "4!a21!c"),
+    ("RO", 24, "4!a16!c"),
+    ("RS", 22, "3!n13!n2!n"),
+    ("SA", 24, "2!n18!c"),
+    ("SC", 31, "4!a2!n2!n16!n3!a"),
+    ("SD", 18, "2!n12!n"),
+    ("SE", 24, "3!n16!n1!n"),
+    ("SI", 19, "5!n8!n2!n"),
+    ("SK", 24, "4!n6!n10!n"),
+    ("SM", 27, "1!a5!n5!n12!c"),
+    ("ST", 25, "4!n4!n11!n2!n"),
+    ("SV", 28, "4!a20!n"),
+    ("TL", 23, "3!n14!n2!n"),
+    ("TN", 24, "2!n3!n13!n2!n"),
+    ("TR", 26, "5!n1!n16!c"),
+    ("UA", 29, "6!n19!c"),
+    ("VA", 22, "3!n15!n"),
+    ("VG", 24, "4!a16!n"),
+    ("XK", 20, "4!n10!n2!n"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IBAN {
+    pub country_code: String,
+    pub check_digits: String,
+    pub bban: String,
+}
+
+impl IBAN {
+    /// The IBAN with no spaces, as used in electronic transfers.
+    pub fn electronic(&self) -> String {
+        format!("{}{}{}", self.country_code, self.check_digits, self.bban)
+    }
+
+    /// The IBAN split into groups of four characters, as printed on paper.
+    pub fn print(&self) -> String {
+        self.electronic()
+            .chars()
+            .collect::<Vec<char>>()
+            .chunks(4)
+            .map(|chunk| chunk.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for IBAN {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print())
+    }
+}
+
+/// Splits a BBAN structure such as `4!a6!n8!n` into (count, kind) pairs.
+fn parse_structure(structure: &str) -> Vec<(usize, char)> {
+    let mut parts = Vec::new();
+    let mut count = 0;
+    for c in structure.chars() {
+        match c {
+            '0'..='9' => count = count * 10 + c.to_digit(10).unwrap() as usize,
+            '!' => {}
+            kind => {
+                parts.push((count, kind));
+                count = 0;
+            }
+        }
+    }
+    parts
+}
+
+fn bban_matches(bban: &str, structure: &str) -> bool {
+    let mut characters = bban.chars();
+
+    for (count, kind) in parse_structure(structure) {
+        for _ in 0..count {
+            let valid = match (characters.next(), kind) {
+                (Some(c), 'n') => c.is_ascii_digit(),
+                (Some(c), 'a') => c.is_ascii_uppercase(),
+                (Some(c), 'c') => c.is_ascii_digit() || c.is_ascii_uppercase(),
+                _ => false,
+            };
+            if !valid {
+                return false;
+            }
+        }
+    }
+
+    characters.next().is_none()
+}
+
+fn find_country(country_code: &str) -> Result<(usize, &'static str), IBANVerificationError> {
+    COUNTRIES
+        .iter()
+        .find(|(code, _, _)| *code == country_code)
+        .map(|&(_, length, structure)| (length, structure))
+        .ok_or(IBANVerificationError::UnknownCountry)
+}
+
+fn check_bban(country_code: &str, bban: &str) -> Result<(), IBANVerificationError> {
+    let (length, structure) = find_country(country_code)?;
+
+    if bban.len() + 4 != length {
+        return Err(IBANVerificationError::InvalidLength);
+    }
+
+    if !bban_matches(bban, structure) {
+        return Err(IBANVerificationError::InvalidBBANFormat);
+    }
+
+    Ok(())
+}
+
+/// Parses an IBAN in either electronic or print format, checking its length and BBAN structure
+/// against its country's entry in the registry and its check digits with ISO 7064 MOD 97-10.
+/// ISO 13616 only issues check digits from 02 to 98, so 00, 01 and 99 are rejected even though
+/// they can satisfy MOD 97-10.
+pub fn parse_iban(iban: &str) -> Result<IBAN, IBANVerificationError> {
+    let stripped_iban = iban.replace(" ", "").to_ascii_uppercase();
+
+    if !stripped_iban.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(IBANVerificationError::InvalidCharactersFound);
+    }
+
+    if stripped_iban.len() < 4 {
+        return Err(IBANVerificationError::InvalidLength);
+    }
+
+    let (country_code, rest) = stripped_iban.split_at(2);
+    let (check_digits, bban) = rest.split_at(2);
+
+    check_bban(country_code, bban)?;
+
+    if !check_digits.chars().all(|c| c.is_ascii_digit())
+        || !(2..=98).contains(&check_digits.parse::<u8>().unwrap())
+        || Iso7064Mod97
+            .verify(&format!("{}{}{}", bban, country_code, check_digits))
+            .is_err()
+    {
+        return Err(IBANVerificationError::InvalidCheckDigits);
+    }
+
+    Ok(IBAN {
+        country_code: String::from(country_code),
+        check_digits: String::from(check_digits),
+        bban: String::from(bban),
+    })
+}
+
+pub fn verify_iban(iban: &str) -> Result<(), IBANVerificationError> {
+    parse_iban(iban).map(|_| ())
+}
+
+/// Computes the two check digits for a country code and BBAN.
+pub fn iban_check_digits(country_code: &str, bban: &str) -> Result<String, IBANVerificationError> {
+    let country_code = country_code.to_ascii_uppercase();
+    let bban = bban.replace(" ", "").to_ascii_uppercase();
+
+    if !bban.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(IBANVerificationError::InvalidCharactersFound);
+    }
+
+    check_bban(&country_code, &bban)?;
+
+    Iso7064Mod97
+        .compute(&format!("{}{}", bban, country_code))
+        .map_err(|_| IBANVerificationError::InvalidCharactersFound)
+}
+
+/// Builds a complete IBAN from a country code and BBAN.
+pub fn generate_iban(country_code: &str, bban: &str) -> Result<IBAN, IBANVerificationError> {
+    let check_digits = iban_check_digits(country_code, bban)?;
+
+    Ok(IBAN {
+        country_code: country_code.to_ascii_uppercase(),
+        check_digits,
+        bban: bban.replace(" ", "").to_ascii_uppercase(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn iban_verification_success() {
+        let valid_iban_list = [
+            "GB82 WEST 1234 5698 7654 32",
+            "DE89370400440532013000",
+            "FR1420041010050500013M02606",
+            "NL91ABNA0417164300",
+            "BE68539007547034",
+            "NO9386011117947",
+            "MT84MALT011000012345MTLCAST001S",
+            "ch9300762011623852957",
+        ];
+
+        valid_iban_list
+            .iter()
+            .for_each(|&iban| assert!(verify_iban(iban).is_ok(), "{}", iban));
+    }
+
+    #[test]
+    pub fn iban_verification_failure() {
+        assert_eq!(
+            verify_iban("GB82 WEST 1234 5698 7654 33"),
+            Err(IBANVerificationError::InvalidCheckDigits)
+        );
+        // 01, 00 and 99 are congruent to the valid 98, 97 and 02 mod 97, but never issued
+        assert!(verify_iban("GB98NWBK60161300000064").is_ok());
+        assert!(verify_iban("GB97NWBK60161300000082").is_ok());
+        assert!(verify_iban("GB02NWBK60161300000046").is_ok());
+        for &iban in [
+            "GB01NWBK60161300000064",
+            "GB00NWBK60161300000082",
+            "GB99NWBK60161300000046",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                verify_iban(iban),
+                Err(IBANVerificationError::InvalidCheckDigits),
+                "{}",
+                iban
+            );
+        }
+        assert_eq!(
+            verify_iban("GB82 WEST 1234 5698 7654 3"),
+            Err(IBANVerificationError::InvalidLength)
+        );
+        assert_eq!(
+            verify_iban("GB82 1234 1234 5698 7654 32"),
+            Err(IBANVerificationError::InvalidBBANFormat)
+        );
+        assert_eq!(verify_iban("XX82WEST12345698765432"), Err(IBANVerificationError::UnknownCountry));
+        assert_eq!(
+            verify_iban("GB82-WEST-1234-5698-7654-32"),
+            Err(IBANVerificationError::InvalidCharactersFound)
+        );
+    }
+
+    #[test]
+    pub fn iban_formatting() {
+        let iban = parse_iban("de89 3704 0044 0532 0130 00").unwrap();
+        assert_eq!(iban.electronic(), "DE89370400440532013000");
+        assert_eq!(iban.print(), "DE89 3704 0044 0532 0130 00");
+        assert_eq!(iban.bban, "370400440532013000");
+    }
+
+    #[test]
+    pub fn iban_generation() {
+        assert_eq!(iban_check_digits("GB", "WEST12345698765432"), Ok(String::from("82")));
+        assert_eq!(
+            generate_iban("DE", "370400440532013000").unwrap().electronic(),
+            "DE89370400440532013000"
+        );
+        assert_eq!(
+            iban_check_digits("DE", "37040044053201300"),
+            Err(IBANVerificationError::InvalidLength)
+        );
+    }
+
+    #[test]
+    pub fn country_table_is_consistent() {
+        for (country_code, length, structure) in COUNTRIES.iter() {
+            let bban_length: usize = parse_structure(structure).iter().map(|(count, _)| count).sum();
+            assert_eq!(bban_length + 4, *length, "{}", country_code);
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use super::hash::HashAlgorithm;
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum HashType {
+    MD4,
+    MD5,
+    NTLM,
+    SHA1,
+    RIPEMD160,
+    SHA224,
+    SHA256,
+    SHA384,
+    SHA512,
+    SHA3_256,
+    BLAKE2b,
+    BLAKE3,
+    /// `*` followed by an upper case SHA-1 of SHA-1, as stored by MySQL 4.1 onwards.
+    MySQL5,
+    /// `{SHA}`, `{SSHA}` and `{MD5}` base64 digests from LDAP directories.
+    LdapSHA,
+    LdapSSHA,
+    LdapMD5,
+    MD5Crypt,
+    APR1,
+    SHA256Crypt,
+    SHA512Crypt,
+    BCrypt,
+    Argon2i,
+    Argon2d,
+    Argon2id,
+    Scrypt,
+    PBKDF2,
+}
+
+impl HashType {
+    /// The hash function behind types that are a plain unsalted digest, which are the ones the
+    /// crackers can work on.
+    pub fn algorithm(self) -> Option<HashAlgorithm> {
+        match self {
+            HashType::MD5 | HashType::LdapMD5 => Some(HashAlgorithm::MD5),
+            HashType::SHA1 | HashType::LdapSHA => Some(HashAlgorithm::SHA1),
+            HashType::SHA224 => Some(HashAlgorithm::SHA224),
+            HashType::SHA256 => Some(HashAlgorithm::SHA256),
+            HashType::SHA384 => Some(HashAlgorithm::SHA384),
+            HashType::SHA512 => Some(HashAlgorithm::SHA512),
+            HashType::SHA3_256 => Some(HashAlgorithm::SHA3_256),
+            HashType::BLAKE2b => Some(HashAlgorithm::BLAKE2b),
+            HashType::BLAKE3 => Some(HashAlgorithm::BLAKE3),
+            _ => None,
+        }
+    }
+
+    /// Whether the hash includes a salt, which rules out precomputed tables and multi-target
+    /// cracking.
+    pub fn salted(self) -> bool {
+        matches!(
+            self,
+            HashType::LdapSSHA
+                | HashType::MD5Crypt
+                | HashType::APR1
+                | HashType::SHA256Crypt
+                | HashType::SHA512Crypt
+                | HashType::BCrypt
+                | HashType::Argon2i
+                | HashType::Argon2d
+                | HashType::Argon2id
+                | HashType::Scrypt
+                | HashType::PBKDF2
+        )
+    }
+}
+
+impl fmt::Display for HashType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm() {
+            Some(algorithm) if !matches!(self, HashType::LdapSHA | HashType::LdapMD5) => {
+                write!(f, "{}", algorithm)
+            }
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+/// Prefixes of the modular crypt and PHC formats, checked in order so `$argon2id$` isn't taken for
+/// `$argon2i$`.
+const PREFIXES: [(&str, HashType); 14] = [
+    ("$1$", HashType::MD5Crypt),
+    ("$apr1$", HashType::APR1),
+    ("$5$", HashType::SHA256Crypt),
+    ("$6$", HashType::SHA512Crypt),
+    ("$2a$", HashType::BCrypt),
+    ("$2b$", HashType::BCrypt),
+    ("$2x$", HashType::BCrypt),
+    ("$2y$", HashType::BCrypt),
+    ("$argon2id$", HashType::Argon2id),
+    ("$argon2i$", HashType::Argon2i),
+    ("$argon2d$", HashType::Argon2d),
+    ("$scrypt$", HashType::Scrypt),
+    ("$7$", HashType::Scrypt),
+    ("$pbkdf2", HashType::PBKDF2),
+];
+
+/// Unsalted hex digests by length, most likely first.
+const HEX_DIGESTS: [(usize, &[HashType]); 6] = [
+    (32, &[HashType::MD5, HashType::NTLM, HashType::MD4]),
+    (40, &[HashType::SHA1, HashType::RIPEMD160]),
+    (56, &[HashType::SHA224]),
+    (64, &[HashType::SHA256, HashType::SHA3_256, HashType::BLAKE3]),
+    (96, &[HashType::SHA384]),
+    (128, &[HashType::SHA512, HashType::BLAKE2b]),
+];
+
+fn is_hex(input: &str) -> bool {
+    !input.is_empty() && input.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    base64::decode(input).ok()
+}
+
+/// Returns the hash types `hash` could be, most likely first, judging by its prefix, length and
+/// character set. An empty list means it isn't any format this module knows.
+pub fn identify(hash: &str) -> Vec<HashType> {
+    let hash = hash.trim();
+
+    if let Some(&(_, hash_type)) = PREFIXES.iter().find(|(prefix, _)| hash.starts_with(prefix)) {
+        // bcrypt is always a two digit cost and 53 characters of salt and hash
+        if hash_type == HashType::BCrypt {
+            let rest = hash[4..].as_bytes();
+            if rest.len() != 56 || !rest[0].is_ascii_digit() || !rest[1].is_ascii_digit() || rest[2] != b'$' {
+                return Vec::new();
+            }
+        }
+        return vec![hash_type];
+    }
+
+    if let Some(digest) = hash.strip_prefix("{SHA}") {
+        return match decode_base64(digest) {
+            Some(bytes) if bytes.len() == 20 => vec![HashType::LdapSHA],
+            _ => Vec::new(),
+        };
+    }
+    if let Some(digest) = hash.strip_prefix("{SSHA}") {
+        return match decode_base64(digest) {
+            Some(bytes) if bytes.len() > 20 => vec![HashType::LdapSSHA],
+            _ => Vec::new(),
+        };
+    }
+    if let Some(digest) = hash.strip_prefix("{MD5}") {
+        return match decode_base64(digest) {
+            Some(bytes) if bytes.len() == 16 => vec![HashType::LdapMD5],
+            _ => Vec::new(),
+        };
+    }
+
+    if let Some(digest) = hash.strip_prefix('*') {
+        return if digest.len() == 40 && is_hex(digest) {
+            vec![HashType::MySQL5]
+        } else {
+            Vec::new()
+        };
+    }
+
+    if !is_hex(hash) {
+        return Vec::new();
+    }
+    let mut candidates: Vec<HashType> = HEX_DIGESTS
+        .iter()
+        .find(|(length, _)| *length == hash.len())
+        .map(|(_, types)| types.to_vec())
+        .unwrap_or_default();
+
+    // NTLM hashes are conventionally written in upper case, MD5 in lower
+    if hash.len() == 32 && hash.chars().any(|c| c.is_ascii_uppercase()) {
+        candidates.swap(0, 1);
+    }
+    candidates
+}
+
+/// For a hash the crackers can attack, the most likely algorithm and the raw digest to search
+/// for. Hex digests and the unsalted LDAP formats are accepted.
+pub fn unsalted_digest(hash: &str) -> Option<(HashType, HashAlgorithm, Vec<u8>)> {
+    let hash = hash.trim();
+    let hash_type = identify(hash).into_iter().find(|t| t.algorithm().is_some())?;
+    let digest = match hash_type {
+        HashType::LdapSHA => decode_base64(&hash[5..])?,
+        HashType::LdapMD5 => decode_base64(&hash[5..])?,
+        _ => hex::decode(hash).ok()?,
+    };
+    Some((hash_type, hash_type.algorithm()?, digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn raw_digests() {
+        assert_eq!(
+            identify("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"),
+            vec![HashType::SHA1, HashType::RIPEMD160]
+        );
+        assert_eq!(identify(&HashAlgorithm::MD5.hex_digest(b"a"))[0], HashType::MD5);
+        assert_eq!(identify("8846F7EAEE8FB117AD06BDD830B7586C")[0], HashType::NTLM);
+        assert_eq!(identify(&HashAlgorithm::SHA256.hex_digest(b"a"))[0], HashType::SHA256);
+        assert_eq!(identify(&HashAlgorithm::SHA512.hex_digest(b"a"))[0], HashType::SHA512);
+        assert_eq!(
+            identify(&HashAlgorithm::SHA384.hex_digest(b"a")),
+            vec![HashType::SHA384]
+        );
+        assert!(identify("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434").is_empty());
+        assert!(identify("zzf4c61ddcc5e8a2dabede0f3b482cd9aea9434d").is_empty());
+        assert!(identify("").is_empty());
+    }
+
+    #[test]
+    pub fn prefixed_formats() {
+        let hashes = [
+            ("$1$saltsalt$qjXMvbEw8oaL.CzflDugX/", HashType::MD5Crypt),
+            ("$apr1$71850310$gh9m4xcAn3MGxogwX/ztb.", HashType::APR1),
+            (
+                "$5$rounds=5000$salt$Gbv1yNY2PnYpFb6x3gWnxPVxd46IjgFLV.LGnObDIT0",
+                HashType::SHA256Crypt,
+            ),
+            (
+                "$6$salt$IxDD3jeSOb5eB1CX5LBsqZFVkJdido3OUILO5Ifz5iwMuTS4XMS130MTSuDDl3aCI6WouIL9AjRbLCelDCy.g.",
+                HashType::SHA512Crypt,
+            ),
+            (
+                "$2b$12$GhvMmNVjRW29ulnudl.LbuAnUtN/LRfe1JsBm1Xu6LE3059z5Tr8m",
+                HashType::BCrypt,
+            ),
+            (
+                "$argon2id$v=19$m=256,t=2,p=2$c29tZXNhbHQ$bQk8UB/VmZZF4Oo79iDXuL5/0ttZwg2f/5U52iv1cDc",
+                HashType::Argon2id,
+            ),
+            (
+                "$argon2i$v=19$m=65536,t=2,p=4$c29tZXNhbHQ$RdescudvJCsgt3ub+b+dWRWJTmaaJObG",
+                HashType::Argon2i,
+            ),
+            (
+                "$scrypt$ln=4,r=8,p=1$TmFDbA$9d+zly55CLIkEMXF83iJB829GnmXGxIndQK9Snfm1dM",
+                HashType::Scrypt,
+            ),
+            (
+                "$pbkdf2-sha256$i=1$c2FsdA$Eg+2z/z4syxD5yJSVsT4N6hlSMkszDVICAWYfLcL4Xs",
+                HashType::PBKDF2,
+            ),
+            ("{SHA}qvTGHdzF6KLavt4PO0gs2a6pQ00=", HashType::LdapSHA),
+            ("{SSHA}DkMTwBl+a/3DQTxCYEApdUtNXGgdUac3", HashType::LdapSSHA),
+            ("{MD5}XUFAKrxLKna5cZ2REBfFkg==", HashType::LdapMD5),
+            ("*6BB4837EB74329105EE4568DDA7DC67ED2CA2AD9", HashType::MySQL5),
+        ];
+
+        for (hash, hash_type) in hashes.iter() {
+            assert_eq!(identify(hash), vec![*hash_type], "{}", hash);
+        }
+
+        assert!(identify("$2b$12$tooshort").is_empty());
+        assert!(identify("{SHA}notbase64!").is_empty());
+    }
+
+    #[test]
+    pub fn crackable_digests() {
+        let (hash_type, algorithm, digest) = unsalted_digest("{SHA}qvTGHdzF6KLavt4PO0gs2a6pQ00=").unwrap();
+        assert_eq!(hash_type, HashType::LdapSHA);
+        assert_eq!(algorithm, HashAlgorithm::SHA1);
+        assert_eq!(digest, HashAlgorithm::SHA1.digest(b"hello"));
+
+        let (_, algorithm, _) = unsalted_digest("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d").unwrap();
+        assert_eq!(algorithm, HashAlgorithm::SHA1);
+
+        // NTLM has no implementation, so an upper case 32 character digest falls back to MD5
+        let (hash_type, _, _) = unsalted_digest("8846F7EAEE8FB117AD06BDD830B7586C").unwrap();
+        assert_eq!(hash_type, HashType::MD5);
+
+        assert!(unsalted_digest("$2b$12$GhvMmNVjRW29ulnudl.LbuAnUtN/LRfe1JsBm1Xu6LE3059z5Tr8m").is_none());
+        assert!(HashType::BCrypt.salted());
+        assert!(!HashType::SHA1.salted());
+    }
+}
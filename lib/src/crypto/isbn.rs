@@ -1,10 +1,35 @@
+use super::checkdigit;
+
+use serde::Serialize;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum ISBNFormat {
+    ISBN10,
+    ISBN13,
+}
+
+impl fmt::Display for ISBNFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ISBNFormat::ISBN10 => write!(f, "ISBN-10"),
+            ISBNFormat::ISBN13 => write!(f, "ISBN-13"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub enum ISBNVerificationError {
     InvalidDigitCount,
-    NonValidISBN,
-    InvalidDigitsFound,
+    NonValidISBN(ISBNFormat),
+    InvalidDigitsFound(ISBNFormat),
+    /// An ISBN-13 that isn't in the 978 or 979 Bookland ranges.
+    InvalidPrefix,
+    /// Only 978-prefixed ISBN-13s have an ISBN-10 equivalent.
+    NotConvertible,
+    /// The ISBN falls outside the ranges listed in the embedded range message. The embedded copy
+    /// is trimmed, so this includes ISBNs from real registration groups it leaves out.
+    UnknownRange,
 }
 
 impl fmt::Display for ISBNVerificationError {
@@ -13,42 +38,277 @@ impl fmt::Display for ISBNVerificationError {
     }
 }
 
-pub fn verify_isbn(isbn: &str) -> Result<(), ISBNVerificationError> {
-    let mut error_digit: i32 = -1;
+/// Verifies an ISBN-10 or ISBN-13, picking the format from the number of digits once hyphens have
+/// been removed. The detected format is returned.
+pub fn verify_isbn(isbn: &str) -> Result<ISBNFormat, ISBNVerificationError> {
+    match isbn.replace("-", "").len() {
+        10 => verify_isbn10(isbn).map(|_| ISBNFormat::ISBN10),
+        13 => verify_isbn13(isbn).map(|_| ISBNFormat::ISBN13),
+        _ => Err(ISBNVerificationError::InvalidDigitCount),
+    }
+}
 
+pub fn verify_isbn10(isbn: &str) -> Result<(), ISBNVerificationError> {
     let mut stripped_isbn = isbn.replace("-", "");
 
     if stripped_isbn.len() != 10 {
         return Err(ISBNVerificationError::InvalidDigitCount);
     }
 
-    if stripped_isbn.ends_with('X') {
-        error_digit = 10;
-        stripped_isbn.pop();
+    let check_digit = stripped_isbn.pop().unwrap();
+    if !check_digit.is_ascii_digit() && check_digit != 'X' {
+        return Err(ISBNVerificationError::InvalidDigitsFound(ISBNFormat::ISBN10));
     }
 
-    if !stripped_isbn.chars().all(char::is_numeric) {
-        return Err(ISBNVerificationError::InvalidDigitsFound);
+    if check_digit != isbn10_check_digit(&stripped_isbn)? {
+        Err(ISBNVerificationError::NonValidISBN(ISBNFormat::ISBN10))
+    } else {
+        Ok(())
     }
+}
+
+/// Verifies an ISBN-13, which must be an EAN-13 in the 978 or 979 ranges.
+pub fn verify_isbn13(isbn: &str) -> Result<(), ISBNVerificationError> {
+    verify_ean13(isbn)?;
 
-    if error_digit == -1 {
-        error_digit = stripped_isbn.pop().unwrap().to_digit(10).unwrap() as i32;
+    let stripped_isbn = isbn.replace("-", "");
+    if stripped_isbn.starts_with("978") || stripped_isbn.starts_with("979") {
+        Ok(())
+    } else {
+        Err(ISBNVerificationError::InvalidPrefix)
     }
+}
 
-    if stripped_isbn
-        .chars()
-        .enumerate()
-        .map(|(i, c)| (i as i32 + 1) * c.to_digit(10).unwrap() as i32)
-        .sum::<i32>()
-        % 11
-        != error_digit
-    {
-        Err(ISBNVerificationError::NonValidISBN)
+/// Verifies the check digit of any EAN-13 barcode number.
+pub fn verify_ean13(ean: &str) -> Result<(), ISBNVerificationError> {
+    let mut stripped_ean = ean.replace("-", "");
+
+    if stripped_ean.len() != 13 {
+        return Err(ISBNVerificationError::InvalidDigitCount);
+    }
+
+    let check_digit = stripped_ean.pop().unwrap();
+    if !check_digit.is_ascii_digit() {
+        return Err(ISBNVerificationError::InvalidDigitsFound(ISBNFormat::ISBN13));
+    }
+
+    if check_digit != isbn13_check_digit(&stripped_ean)? {
+        Err(ISBNVerificationError::NonValidISBN(ISBNFormat::ISBN13))
     } else {
         Ok(())
     }
 }
 
+/// Computes the check digit for the first nine digits of an ISBN-10. A check value of 10 is
+/// written as 'X'.
+pub fn isbn10_check_digit(isbn: &str) -> Result<char, ISBNVerificationError> {
+    let digits = parse_digits(isbn, 9, ISBNFormat::ISBN10)?;
+
+    let check_digit = digits
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (i as u32 + 1) * d)
+        .sum::<u32>()
+        % 11;
+
+    Ok(std::char::from_digit(check_digit, 10).unwrap_or('X'))
+}
+
+/// Computes the check digit for the first twelve digits of an ISBN-13 (or any EAN-13), weighting
+/// the digits alternately by 1 and 3.
+pub fn isbn13_check_digit(isbn: &str) -> Result<char, ISBNVerificationError> {
+    let digits = parse_digits(isbn, 12, ISBNFormat::ISBN13)?;
+
+    let sum = digits
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { *d } else { 3 * d })
+        .sum::<u32>();
+
+    Ok(std::char::from_digit((10 - sum % 10) % 10, 10).unwrap())
+}
+
+/// Converts an ISBN-10 to its 978-prefixed ISBN-13. The result has no hyphens.
+pub fn isbn10_to_isbn13(isbn: &str) -> Result<String, ISBNVerificationError> {
+    verify_isbn10(isbn)?;
+
+    let mut isbn13 = format!("978{}", &isbn.replace("-", "")[..9]);
+    isbn13.push(isbn13_check_digit(&isbn13)?);
+    Ok(isbn13)
+}
+
+/// Converts a 978-prefixed ISBN-13 to an ISBN-10. The result has no hyphens.
+pub fn isbn13_to_isbn10(isbn: &str) -> Result<String, ISBNVerificationError> {
+    verify_isbn13(isbn)?;
+
+    let stripped_isbn = isbn.replace("-", "");
+    if !stripped_isbn.starts_with("978") {
+        return Err(ISBNVerificationError::NotConvertible);
+    }
+
+    let mut isbn10 = String::from(&stripped_isbn[3..12]);
+    isbn10.push(isbn10_check_digit(&isbn10)?);
+    Ok(isbn10)
+}
+
+fn parse_digits(input: &str, length: usize, format: ISBNFormat) -> Result<Vec<u32>, ISBNVerificationError> {
+    let stripped_input = input.replace("-", "");
+
+    if stripped_input.len() != length {
+        return Err(ISBNVerificationError::InvalidDigitCount);
+    }
+
+    stripped_input
+        .chars()
+        .map(|c| if c.is_ascii_digit() { c.to_digit(10) } else { None })
+        .collect::<Option<Vec<u32>>>()
+        .ok_or(ISBNVerificationError::InvalidDigitsFound(format))
+}
+
+/// Embedded copy of the International ISBN Agency's range message, used to split ISBNs into parts.
+/// Only a handful of registration groups are included so far; the rest give `UnknownRange` until
+/// the file is replaced with a full export of RangeMessage.xml, which is read as published.
+const RANGE_MESSAGE: &str = include_str!("isbn_ranges.xml");
+
+/// The parts of an ISBN. `prefix` is `None` for ISBN-10s.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ISBNParts {
+    pub prefix: Option<String>,
+    pub registration_group: String,
+    pub registration_group_agency: String,
+    pub registrant: String,
+    pub publication: String,
+    pub check_digit: char,
+}
+
+impl fmt::Display for ISBNParts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(prefix) = &self.prefix {
+            write!(f, "{}-", prefix)?;
+        }
+        write!(
+            f,
+            "{}-{}-{}-{}",
+            self.registration_group, self.registrant, self.publication, self.check_digit
+        )
+    }
+}
+
+struct RangeRule {
+    low: u32,
+    high: u32,
+    length: usize,
+}
+
+/// Returns the text inside every `<tag>...</tag>` element in `xml`. Elements of the same name must
+/// not be nested, which holds for the range message.
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        match rest.find(&close) {
+            Some(end) => {
+                elements.push(rest[..end].trim());
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    elements
+}
+
+/// Finds the `EAN.UCC` or `Group` element with the given prefix, returning its agency and rules.
+fn find_ranges(tag: &str, prefix: &str) -> Option<(String, Vec<RangeRule>)> {
+    let element = xml_elements(RANGE_MESSAGE, tag)
+        .into_iter()
+        .find(|element| xml_elements(element, "Prefix").first() == Some(&prefix))?;
+
+    let agency = xml_elements(element, "Agency").first().unwrap_or(&"").to_string();
+    let rules = xml_elements(element, "Rule")
+        .iter()
+        .filter_map(|rule| {
+            let range = xml_elements(rule, "Range").first()?.to_string();
+            let mut bounds = range.split('-');
+            Some(RangeRule {
+                low: bounds.next()?.parse().ok()?,
+                high: bounds.next()?.parse().ok()?,
+                length: xml_elements(rule, "Length").first()?.parse().ok()?,
+            })
+        })
+        .collect();
+
+    Some((agency, rules))
+}
+
+/// Looks up how many of the leading digits of `digits` belong to the next part of the ISBN. The
+/// range message compares the next seven digits, padded with zeros.
+fn range_length(rules: &[RangeRule], digits: &str) -> Option<usize> {
+    let mut padded: String = digits.chars().take(7).collect();
+    while padded.len() < 7 {
+        padded.push('0');
+    }
+    let value: u32 = padded.parse().ok()?;
+
+    rules
+        .iter()
+        .find(|rule| rule.low <= value && value <= rule.high)
+        .map(|rule| rule.length)
+        .filter(|&length| length > 0 && length <= digits.len())
+}
+
+/// Splits a valid ISBN-10 or ISBN-13 into its prefix, registration group, registrant, publication
+/// and check digit. Hyphens in the input are ignored.
+pub fn parse_isbn(isbn: &str) -> Result<ISBNParts, ISBNVerificationError> {
+    let format = verify_isbn(isbn)?;
+    let stripped_isbn = isbn.replace("-", "");
+
+    // ISBN-10s are split using the 978 rules, which is the prefix they'd have as an ISBN-13.
+    let (prefix, body) = match format {
+        ISBNFormat::ISBN10 => ("978", &stripped_isbn[..9]),
+        ISBNFormat::ISBN13 => (&stripped_isbn[..3], &stripped_isbn[3..12]),
+    };
+
+    let (_, prefix_rules) = find_ranges("EAN.UCC", prefix).ok_or(ISBNVerificationError::UnknownRange)?;
+    let group_length = range_length(&prefix_rules, body).ok_or(ISBNVerificationError::UnknownRange)?;
+    let registration_group = &body[..group_length];
+
+    let (agency, group_rules) = find_ranges("Group", &format!("{}-{}", prefix, registration_group))
+        .ok_or(ISBNVerificationError::UnknownRange)?;
+    let registrant_length =
+        range_length(&group_rules, &body[group_length..]).ok_or(ISBNVerificationError::UnknownRange)?;
+    if group_length + registrant_length >= body.len() {
+        return Err(ISBNVerificationError::UnknownRange);
+    }
+
+    Ok(ISBNParts {
+        prefix: match format {
+            ISBNFormat::ISBN10 => None,
+            ISBNFormat::ISBN13 => Some(String::from(prefix)),
+        },
+        registration_group: String::from(registration_group),
+        registration_group_agency: agency,
+        registrant: String::from(&body[group_length..group_length + registrant_length]),
+        publication: String::from(&body[group_length + registrant_length..]),
+        check_digit: stripped_isbn.chars().last().unwrap(),
+    })
+}
+
+/// Formats an ISBN with hyphens in the places given by the range message, whatever hyphens the
+/// input had.
+pub fn hyphenate_isbn(isbn: &str) -> Result<String, ISBNVerificationError> {
+    parse_isbn(isbn).map(|parts| parts.to_string())
+}
+
+/// Lists every valid ISBN that is one character substitution or one swap of adjacent characters
+/// away from the given ISBN, for offering "did you mean" choices. Hyphens in the input are kept in
+/// place in the suggestions.
+pub fn suggest_corrections(isbn: &str) -> Vec<String> {
+    checkdigit::suggest_corrections(isbn, "0123456789X", &['-'], |candidate| verify_isbn(candidate).is_ok())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,9 +339,15 @@ mod tests {
 
     #[test]
     pub fn isbn_verification_digit_count() {
-        let invalid_isbn = "99921-58-10-756";
-
-        assert!(verify_isbn(&String::from(invalid_isbn)).is_err()); // TODO: match error
+        // Between the ISBN-10 and ISBN-13 lengths
+        for invalid_isbn in ["99921-58-10-75", "99921-58-10-756"].iter() {
+            assert_eq!(
+                verify_isbn(invalid_isbn),
+                Err(ISBNVerificationError::InvalidDigitCount),
+                "{}",
+                invalid_isbn
+            );
+        }
     }
 
     #[test]
@@ -90,4 +356,106 @@ mod tests {
 
         assert!(verify_isbn(&String::from(invalid_isbn)).is_err()); // TODO: match error
     }
+
+    #[test]
+    pub fn isbn_correction_suggestions() {
+        let suggestions = suggest_corrections("0-2-83-56131-5");
+        assert!(suggestions.contains(&String::from("0-2-33-56131-5")));
+        assert!(suggestions.iter().all(|isbn| verify_isbn(isbn).is_ok()));
+
+        let suggestions = suggest_corrections("960-425-509-0");
+        assert!(suggestions.contains(&String::from("960-425-059-0")));
+
+        let suggestions = suggest_corrections("0-9752298-0-9");
+        assert!(suggestions.contains(&String::from("0-9752298-0-X")));
+    }
+
+    #[test]
+    pub fn isbn13_verification() {
+        let valid_isbn_list = ["978-0-306-40615-7", "9780262033848", "979-10-90636-07-1"];
+        for isbn in valid_isbn_list.iter() {
+            assert_eq!(verify_isbn(isbn), Ok(ISBNFormat::ISBN13));
+        }
+
+        assert_eq!(
+            verify_isbn("978-0-306-40615-8"),
+            Err(ISBNVerificationError::NonValidISBN(ISBNFormat::ISBN13))
+        );
+        assert_eq!(
+            verify_isbn("978-0-306-4061A-7"),
+            Err(ISBNVerificationError::InvalidDigitsFound(ISBNFormat::ISBN13))
+        );
+        assert_eq!(verify_isbn("4006381333931"), Err(ISBNVerificationError::InvalidPrefix));
+        assert!(verify_ean13("4006381333931").is_ok());
+    }
+
+    #[test]
+    pub fn isbn_check_digits() {
+        assert_eq!(isbn10_check_digit("0-9752298-0"), Ok('X'));
+        assert_eq!(isbn10_check_digit("030640615"), Ok('2'));
+        assert_eq!(isbn13_check_digit("978030640615"), Ok('7'));
+        assert_eq!(isbn13_check_digit("97803064061"), Err(ISBNVerificationError::InvalidDigitCount));
+    }
+
+    #[test]
+    pub fn isbn_conversion() {
+        assert_eq!(isbn10_to_isbn13("0-306-40615-2"), Ok(String::from("9780306406157")));
+        assert_eq!(isbn10_to_isbn13("0-9752298-0-X"), Ok(String::from("9780975229804")));
+        assert_eq!(isbn13_to_isbn10("978-0-306-40615-7"), Ok(String::from("0306406152")));
+        assert_eq!(isbn13_to_isbn10("9780975229804"), Ok(String::from("097522980X")));
+        assert_eq!(
+            isbn13_to_isbn10("979-10-90636-07-1"),
+            Err(ISBNVerificationError::NotConvertible)
+        );
+        assert_eq!(
+            isbn10_to_isbn13("0-2-83-56131-5"),
+            Err(ISBNVerificationError::NonValidISBN(ISBNFormat::ISBN10))
+        );
+    }
+
+    #[test]
+    pub fn isbn_parsing() {
+        let parts = parse_isbn("9780306406157").unwrap();
+        assert_eq!(parts.prefix, Some(String::from("978")));
+        assert_eq!(parts.registration_group, "0");
+        assert_eq!(parts.registration_group_agency, "English language");
+        assert_eq!(parts.registrant, "306");
+        assert_eq!(parts.publication, "40615");
+        assert_eq!(parts.check_digit, '7');
+
+        let parts = parse_isbn("097522980X").unwrap();
+        assert_eq!(parts.prefix, None);
+        assert_eq!(parts.registrant, "9752298");
+        assert_eq!(parts.check_digit, 'X');
+
+        let parts = parse_isbn("99921-58-10-7").unwrap();
+        assert_eq!(parts.prefix, None);
+        assert_eq!(parts.registration_group, "99921");
+        assert_eq!(parts.registration_group_agency, "Qatar");
+        assert_eq!(parts.registrant, "58");
+        assert_eq!(parts.publication, "10");
+        assert_eq!(parts.check_digit, '7');
+
+        // 979-13 hasn't been assigned to any registration group
+        assert_eq!(parse_isbn("9791300000005"), Err(ISBNVerificationError::UnknownRange));
+        assert_eq!(
+            parse_isbn("0-2-83-56131-5"),
+            Err(ISBNVerificationError::NonValidISBN(ISBNFormat::ISBN10))
+        );
+    }
+
+    #[test]
+    pub fn isbn_hyphenation() {
+        let isbns = [
+            ("9780306406157", "978-0-306-40615-7"),
+            ("0943396042", "0-943396-04-2"),
+            ("1-8435-6028-3", "1-84356-028-3"),
+            ("9783161484100", "978-3-16-148410-0"),
+            ("9791090636071", "979-10-90636-07-1"),
+        ];
+
+        for (input, hyphenated) in isbns.iter() {
+            assert_eq!(hyphenate_isbn(input), Ok(String::from(*hyphenated)));
+        }
+    }
 }
@@ -0,0 +1,170 @@
+//! Length-extension attacks on Merkle–Damgård hashes, showing why `hash(secret || message)` is
+//! not a MAC. A SHA-1 or SHA-256 digest is the hash's full internal state after the padded
+//! message, so anyone holding it can carry on hashing from there without knowing the secret.
+//! HMAC (see the `hmac` module) isn't affected.
+
+use super::hash::HashAlgorithm;
+use super::sha1::{self, Sha1};
+
+use sha2::digest::generic_array::GenericArray;
+use std::fmt;
+
+const BLOCK_LEN: usize = 64;
+
+#[derive(Debug, PartialEq)]
+pub enum LengthExtensionError {
+    /// Only SHA-1 and SHA-256 are supported.
+    UnsupportedAlgorithm(HashAlgorithm),
+    InvalidDigest,
+}
+
+impl fmt::Display for LengthExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension {
+    /// The padding the hash appended to the original input, which ends up between the original
+    /// message and the appended data.
+    pub glue_padding: Vec<u8>,
+    /// The digest of `original input || glue padding || appended data`.
+    pub digest: Vec<u8>,
+}
+
+/// Given the digest of some unknown input of `original_length` bytes, computes the digest of that
+/// input followed by its padding and `append`.
+pub fn extend(
+    algorithm: HashAlgorithm,
+    digest: &[u8],
+    original_length: u64,
+    append: &[u8],
+) -> Result<Extension, LengthExtensionError> {
+    // SHA-1 and SHA-256 pad the same way: 0x80, zeros, then the big endian bit length
+    let glue_padding = sha1::padding(original_length);
+    let resumed_length = original_length + glue_padding.len() as u64;
+
+    let digest = match algorithm {
+        HashAlgorithm::SHA1 => {
+            let state = sha1::from_bytes(digest).ok_or(LengthExtensionError::InvalidDigest)?;
+            // The glue padding always ends on a block boundary
+            let mut hasher = Sha1::from_state(state, resumed_length).unwrap();
+            hasher.update(append);
+            hasher.finish_bytes().to_vec()
+        }
+        HashAlgorithm::SHA256 => {
+            if digest.len() != 32 {
+                return Err(LengthExtensionError::InvalidDigest);
+            }
+            let mut state = [0u32; 8];
+            for (word, chunk) in state.iter_mut().zip(digest.chunks_exact(4)) {
+                *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            }
+
+            let mut tail = append.to_vec();
+            tail.extend(sha1::padding(resumed_length + append.len() as u64));
+            let blocks: Vec<_> = tail
+                .chunks_exact(BLOCK_LEN)
+                .map(GenericArray::clone_from_slice)
+                .collect();
+            sha2::compress256(&mut state, &blocks);
+
+            state.iter().flat_map(|word| word.to_be_bytes().to_vec()).collect()
+        }
+        _ => return Err(LengthExtensionError::UnsupportedAlgorithm(algorithm)),
+    };
+
+    Ok(Extension { glue_padding, digest })
+}
+
+/// Forges a message and digest that verify as `hash(secret || forged message)`, given the digest
+/// of `secret || message` and a guess at the secret's length. Returns the forged message, which
+/// is `message || glue padding || append`, and its digest.
+pub fn forge(
+    algorithm: HashAlgorithm,
+    digest: &[u8],
+    secret_length: usize,
+    message: &[u8],
+    append: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), LengthExtensionError> {
+    let extension = extend(algorithm, digest, (secret_length + message.len()) as u64, append)?;
+    let mut forged = message.to_vec();
+    forged.extend_from_slice(&extension.glue_padding);
+    forged.extend_from_slice(append);
+    Ok((forged, extension.digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret_prefix_mac(algorithm: HashAlgorithm, secret: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut input = secret.to_vec();
+        input.extend_from_slice(message);
+        algorithm.digest(&input)
+    }
+
+    #[test]
+    pub fn forged_digests_verify() {
+        let message = b"user=alice&role=guest";
+        let append = b"&role=admin";
+
+        for &algorithm in [HashAlgorithm::SHA1, HashAlgorithm::SHA256].iter() {
+            // Secret lengths either side of the 55 byte point where the padding spills into a
+            // second block
+            for secret_length in [0, 1, 16, 33, 34, 35, 64, 100].iter() {
+                let secret = vec![b'k'; *secret_length];
+                let mac = secret_prefix_mac(algorithm, &secret, message);
+
+                let (forged, digest) = forge(algorithm, &mac, *secret_length, message, append).unwrap();
+                assert!(forged.starts_with(message));
+                assert!(forged.ends_with(append));
+                assert_eq!(
+                    digest,
+                    secret_prefix_mac(algorithm, &secret, &forged),
+                    "{} {}",
+                    algorithm,
+                    secret_length
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn matches_sha1_crate() {
+        let mut original = ::sha1::Sha1::new();
+        original.update(b"secretdata");
+        let extension = extend(HashAlgorithm::SHA1, &original.digest().bytes(), 10, b"more").unwrap();
+
+        let mut extended = ::sha1::Sha1::new();
+        extended.update(b"secretdata");
+        extended.update(&extension.glue_padding);
+        extended.update(b"more");
+        assert_eq!(extension.digest, extended.digest().bytes().to_vec());
+        assert_eq!((10 + extension.glue_padding.len()) % 64, 0);
+    }
+
+    #[test]
+    pub fn wrong_secret_length_fails() {
+        let mac = secret_prefix_mac(HashAlgorithm::SHA256, b"secret", b"message");
+        let (forged, digest) = forge(HashAlgorithm::SHA256, &mac, 7, b"message", b"extra").unwrap();
+        assert_ne!(digest, secret_prefix_mac(HashAlgorithm::SHA256, b"secret", &forged));
+    }
+
+    #[test]
+    pub fn invalid_inputs() {
+        assert_eq!(
+            extend(HashAlgorithm::MD5, &[0; 16], 10, b"x"),
+            Err(LengthExtensionError::UnsupportedAlgorithm(HashAlgorithm::MD5))
+        );
+        assert_eq!(
+            extend(HashAlgorithm::SHA1, &[0; 32], 10, b"x"),
+            Err(LengthExtensionError::InvalidDigest)
+        );
+        assert_eq!(
+            extend(HashAlgorithm::SHA256, &[0; 20], 10, b"x"),
+            Err(LengthExtensionError::InvalidDigest)
+        );
+    }
+}
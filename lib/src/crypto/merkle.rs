@@ -0,0 +1,359 @@
+use super::hash::HashAlgorithm;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Prefixes hashed before leaves and internal nodes, as in RFC 6962, so a leaf can never be passed
+/// off as an internal node or the other way round.
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+#[derive(Debug, PartialEq)]
+pub enum MerkleError {
+    InvalidChunkSize,
+    IndexOutOfRange(usize),
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Hashing settings shared by a tree and the proofs it produces.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerkleConfig {
+    pub algorithm: HashAlgorithm,
+    pub domain_separation: bool,
+}
+
+impl MerkleConfig {
+    fn hash_leaf(&self, chunk: &[u8]) -> Vec<u8> {
+        let mut hasher = self.algorithm.hasher();
+        if self.domain_separation {
+            hasher.update(&[LEAF_PREFIX]);
+        }
+        hasher.update(chunk);
+        hasher.finish()
+    }
+
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        let mut hasher = self.algorithm.hasher();
+        if self.domain_separation {
+            hasher.update(&[NODE_PREFIX]);
+        }
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finish()
+    }
+}
+
+pub struct MerkleTreeBuilder {
+    config: MerkleConfig,
+    chunk_size: usize,
+}
+
+impl MerkleTreeBuilder {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        Self {
+            config: MerkleConfig {
+                algorithm,
+                domain_separation: true,
+            },
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Whether leaves and internal nodes are hashed with distinct prefixes. On by default; turning
+    /// it off gives the plain `H(left || right)` construction some other tools use.
+    pub fn domain_separation(mut self, domain_separation: bool) -> Self {
+        self.config.domain_separation = domain_separation;
+        self
+    }
+
+    pub fn build(&self, data: &[u8]) -> Result<MerkleTree, MerkleError> {
+        // Reading from a slice can't fail, so the chunk size is the only thing to go wrong
+        self.build_from_reader(data).map_err(|_| MerkleError::InvalidChunkSize)
+    }
+
+    /// Reads and hashes one chunk at a time, so only the leaf hashes are kept in memory.
+    pub fn build_from_reader<R: Read>(&self, mut reader: R) -> io::Result<MerkleTree> {
+        if self.chunk_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chunk size must be positive",
+            ));
+        }
+
+        let mut leaves = Vec::new();
+        let mut chunk = vec![0u8; self.chunk_size];
+        loop {
+            let length = read_chunk(&mut reader, &mut chunk)?;
+            // An empty input still gets one (empty) leaf, so every tree has a root
+            if length > 0 || leaves.is_empty() {
+                leaves.push(self.config.hash_leaf(&chunk[..length]));
+            }
+            if length < self.chunk_size {
+                break;
+            }
+        }
+
+        Ok(MerkleTree::from_leaves(self.config, self.chunk_size, leaves))
+    }
+
+    pub fn build_from_file<P: AsRef<Path>>(&self, path: P) -> io::Result<MerkleTree> {
+        self.build_from_reader(File::open(path)?)
+    }
+}
+
+/// Fills `chunk` from `reader`, returning fewer bytes only at the end of the input.
+fn read_chunk<R: Read>(reader: &mut R, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < chunk.len() {
+        match reader.read(&mut chunk[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(filled)
+}
+
+/// A binary hash tree over fixed-size chunks of some data. When a level has an odd number of
+/// nodes the last one is carried up unchanged rather than paired with itself, so no two different
+/// inputs share a root through duplicated nodes.
+pub struct MerkleTree {
+    config: MerkleConfig,
+    chunk_size: usize,
+    /// Every level of the tree, from the leaf hashes up to the root.
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleTree {
+    fn from_leaves(config: MerkleConfig, chunk_size: usize, leaves: Vec<Vec<u8>>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => config.hash_node(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Self {
+            config,
+            chunk_size,
+            levels,
+        }
+    }
+
+    pub fn config(&self) -> MerkleConfig {
+        self.config
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.levels.last().unwrap()[0]
+    }
+
+    pub fn root_hex(&self) -> String {
+        hex::encode(self.root())
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn leaf(&self, index: usize) -> Option<&[u8]> {
+        self.levels[0].get(index).map(Vec::as_slice)
+    }
+
+    /// The sibling hashes needed to recompute the root from the chunk at `index`.
+    pub fn prove(&self, index: usize) -> Result<InclusionProof, MerkleError> {
+        if index >= self.leaf_count() {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in self.levels.iter().take(self.levels.len() - 1) {
+            if let Some(sibling) = level.get(position ^ 1) {
+                siblings.push(hex::encode(sibling));
+            }
+            position /= 2;
+        }
+
+        Ok(InclusionProof {
+            config: self.config,
+            leaf_index: index,
+            leaf_count: self.leaf_count(),
+            siblings,
+        })
+    }
+}
+
+/// Evidence that a chunk is part of the data a root was computed from. Serialises to JSON with
+/// the sibling hashes in hex, ordered from the leaf upwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub config: MerkleConfig,
+    pub leaf_index: usize,
+    pub leaf_count: usize,
+    pub siblings: Vec<String>,
+}
+
+impl InclusionProof {
+    /// Recomputes the root from `chunk` and the sibling hashes and compares it with `root`. The
+    /// side each sibling is hashed on comes from the leaf index, so the proof only holds for that
+    /// position.
+    pub fn verify(&self, root: &[u8], chunk: &[u8]) -> bool {
+        if self.leaf_index >= self.leaf_count {
+            return false;
+        }
+
+        let mut siblings = self.siblings.iter();
+        let mut hash = self.config.hash_leaf(chunk);
+        let mut position = self.leaf_index;
+        let mut width = self.leaf_count;
+
+        while width > 1 {
+            // The last node of an odd level has no sibling and moves up as it is
+            if position ^ 1 < width {
+                let sibling = match siblings.next().and_then(|sibling| hex::decode(sibling).ok()) {
+                    Some(sibling) => sibling,
+                    None => return false,
+                };
+                hash = if position % 2 == 0 {
+                    self.config.hash_node(&hash, &sibling)
+                } else {
+                    self.config.hash_node(&sibling, &hash)
+                };
+            }
+            position /= 2;
+            width = (width + 1) / 2;
+        }
+
+        siblings.next().is_none() && hash == root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i * 7 % 256) as u8).collect()
+    }
+
+    #[test]
+    pub fn every_chunk_proves() {
+        for &length in [0, 1, 15, 16, 17, 48, 80, 100, 160].iter() {
+            let data = data(length);
+            let tree = MerkleTreeBuilder::new(HashAlgorithm::SHA256)
+                .chunk_size(16)
+                .build(&data)
+                .unwrap();
+            assert_eq!(tree.leaf_count(), ((length + 15) / 16).max(1));
+
+            let chunks: Vec<&[u8]> = if data.is_empty() {
+                vec![&[]]
+            } else {
+                data.chunks(16).collect()
+            };
+            for (index, chunk) in chunks.iter().enumerate() {
+                let proof = tree.prove(index).unwrap();
+                assert!(proof.verify(tree.root(), chunk), "{} {}", length, index);
+                assert!(!proof.verify(tree.root(), b"tampered"), "{} {}", length, index);
+            }
+            assert_eq!(
+                tree.prove(chunks.len()),
+                Err(MerkleError::IndexOutOfRange(chunks.len()))
+            );
+        }
+    }
+
+    #[test]
+    pub fn root_structure() {
+        let tree = MerkleTreeBuilder::new(HashAlgorithm::SHA1)
+            .chunk_size(1)
+            .build(b"abc")
+            .unwrap();
+        let config = tree.config();
+        let (a, b, c) = (config.hash_leaf(b"a"), config.hash_leaf(b"b"), config.hash_leaf(b"c"));
+        assert_eq!(tree.root(), config.hash_node(&config.hash_node(&a, &b), &c).as_slice());
+
+        // Without domain separation a leaf is just the hash of its chunk
+        let plain = MerkleTreeBuilder::new(HashAlgorithm::SHA1)
+            .chunk_size(1)
+            .domain_separation(false)
+            .build(b"abc")
+            .unwrap();
+        assert_eq!(plain.leaf(0).unwrap(), HashAlgorithm::SHA1.digest(b"a").as_slice());
+        assert_ne!(plain.root(), tree.root());
+    }
+
+    #[test]
+    pub fn proofs_are_bound_to_their_position() {
+        let tree = MerkleTreeBuilder::new(HashAlgorithm::BLAKE3)
+            .chunk_size(4)
+            .build(b"aaaabbbbccccdddd")
+            .unwrap();
+        let mut proof = tree.prove(1).unwrap();
+        assert!(proof.verify(tree.root(), b"bbbb"));
+
+        proof.leaf_index = 0;
+        assert!(!proof.verify(tree.root(), b"bbbb"));
+        proof.leaf_index = 1;
+        proof.siblings.pop();
+        assert!(!proof.verify(tree.root(), b"bbbb"));
+    }
+
+    #[test]
+    pub fn proof_serialisation() {
+        let tree = MerkleTreeBuilder::new(HashAlgorithm::SHA256)
+            .chunk_size(8)
+            .build(&data(100))
+            .unwrap();
+        let proof = tree.prove(5).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        let parsed: InclusionProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, proof);
+        assert!(parsed.verify(tree.root(), &data(100)[40..48]));
+    }
+
+    #[test]
+    pub fn streams_files() {
+        let path = std::env::temp_dir().join(format!("crypto-merkle-{}", std::process::id()));
+        std::fs::write(&path, data(5000)).unwrap();
+        let builder = MerkleTreeBuilder::new(HashAlgorithm::SHA256);
+        let from_file = builder.build_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_file.root(), builder.build(&data(5000)).unwrap().root());
+        assert_eq!(from_file.leaf_count(), 5);
+        assert!(MerkleTreeBuilder::new(HashAlgorithm::SHA256)
+            .chunk_size(0)
+            .build(b"x")
+            .is_err());
+    }
+}
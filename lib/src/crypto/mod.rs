@@ -1,8 +1,21 @@
+pub mod analysis;
 pub mod bch;
+pub mod cardgen;
+pub mod checkdigit;
+pub mod collision;
 pub mod cracker;
 pub mod credit;
+pub mod fpe;
 pub mod hamming;
 pub mod hash;
+pub mod hkdf;
+pub mod hmac;
+pub mod iban;
+pub mod identify;
 pub mod isbn;
+pub mod length_extension;
+pub mod merkle;
 mod modular;
+pub mod password;
+pub mod sha1;
 pub mod steg;
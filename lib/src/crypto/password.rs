@@ -0,0 +1,521 @@
+use super::hash::HashAlgorithm;
+use super::hmac::{constant_time_eq, Hmac};
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+const SALT_LEN: usize = 16;
+const HASH_LEN: usize = 32;
+
+/// The only Argon2 version PHC strings are written with, 0x13.
+const ARGON2_VERSION: u32 = 19;
+/// The version an Argon2 PHC string without a `v=` field has, 0x10.
+const ARGON2_DEFAULT_VERSION: u32 = 16;
+
+/// The highest costs accepted, so a PHC string from an untrusted source can't tie up the CPU or
+/// memory for arbitrarily long. Each is well above the recommended minimums.
+pub const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+pub const MAX_SCRYPT_LOG_N: u8 = 20;
+pub const MAX_SCRYPT_R: u32 = 32;
+pub const MAX_SCRYPT_P: u32 = 16;
+/// scrypt uses 128 * r * N bytes, capped at 1 GiB.
+pub const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+pub const MAX_ARGON2_MEMORY_KIB: u32 = 1 << 20;
+pub const MAX_ARGON2_ITERATIONS: u32 = 32;
+pub const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum PasswordHashError {
+    /// The PHC string names an algorithm this module doesn't implement.
+    UnsupportedAlgorithm(String),
+    /// Only version 0x13 of Argon2 is supported, not the original 0x10.
+    UnsupportedVersion(u32),
+    InvalidFormat,
+    InvalidParameters,
+    RandomnessUnavailable,
+}
+
+impl fmt::Display for PasswordHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A password hashing function along with its cost parameters.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PasswordHashParams {
+    Pbkdf2 {
+        algorithm: HashAlgorithm,
+        iterations: u32,
+    },
+    /// scrypt with N = 2^log_n.
+    Scrypt {
+        log_n: u8,
+        r: u32,
+        p: u32,
+    },
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl PasswordHashParams {
+    /// Minimum costs recommended by OWASP's password storage cheat sheet.
+    pub const PBKDF2: Self = PasswordHashParams::Pbkdf2 {
+        algorithm: HashAlgorithm::SHA256,
+        iterations: 600_000,
+    };
+    pub const SCRYPT: Self = PasswordHashParams::Scrypt { log_n: 17, r: 8, p: 1 };
+    pub const ARGON2ID: Self = PasswordHashParams::Argon2id {
+        memory_kib: 19_456,
+        iterations: 2,
+        parallelism: 1,
+    };
+
+    /// Derives `length` bytes from `password` and `salt`.
+    pub fn derive(&self, password: &[u8], salt: &[u8], length: usize) -> Result<Vec<u8>, PasswordHashError> {
+        match *self {
+            PasswordHashParams::Pbkdf2 { algorithm, iterations } => {
+                if iterations == 0 || length == 0 {
+                    return Err(PasswordHashError::InvalidParameters);
+                }
+                Ok(pbkdf2(algorithm, password, salt, iterations, length))
+            }
+            PasswordHashParams::Scrypt { log_n, r, p } => {
+                let params =
+                    scrypt::ScryptParams::new(log_n, r, p).map_err(|_| PasswordHashError::InvalidParameters)?;
+                let mut output = vec![0; length];
+                scrypt::scrypt(password, salt, &params, &mut output)
+                    .map_err(|_| PasswordHashError::InvalidParameters)?;
+                Ok(output)
+            }
+            PasswordHashParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let config = argon2::Config {
+                    variant: argon2::Variant::Argon2id,
+                    version: argon2::Version::Version13,
+                    mem_cost: memory_kib,
+                    time_cost: iterations,
+                    lanes: parallelism,
+                    thread_mode: argon2::ThreadMode::from_threads(parallelism),
+                    secret: &[],
+                    ad: &[],
+                    hash_length: length as u32,
+                };
+                argon2::hash_raw(password, salt, &config).map_err(|_| PasswordHashError::InvalidParameters)
+            }
+        }
+    }
+
+    /// Whether the costs are within the `MAX_` limits.
+    pub fn within_limits(&self) -> bool {
+        match *self {
+            PasswordHashParams::Pbkdf2 { iterations, .. } => iterations <= MAX_PBKDF2_ITERATIONS,
+            PasswordHashParams::Scrypt { log_n, r, p } => {
+                log_n <= MAX_SCRYPT_LOG_N
+                    && r <= MAX_SCRYPT_R
+                    && p <= MAX_SCRYPT_P
+                    && 128 * r as u64 * (1u64 << log_n) <= MAX_SCRYPT_MEMORY
+            }
+            PasswordHashParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                memory_kib <= MAX_ARGON2_MEMORY_KIB
+                    && iterations <= MAX_ARGON2_ITERATIONS
+                    && parallelism <= MAX_ARGON2_PARALLELISM
+            }
+        }
+    }
+
+    fn id(&self) -> String {
+        match self {
+            PasswordHashParams::Pbkdf2 { algorithm, .. } => format!("pbkdf2-{}", algorithm),
+            PasswordHashParams::Scrypt { .. } => String::from("scrypt"),
+            PasswordHashParams::Argon2id { .. } => String::from("argon2id"),
+        }
+    }
+}
+
+impl Default for PasswordHashParams {
+    fn default() -> Self {
+        PasswordHashParams::ARGON2ID
+    }
+}
+
+/// PBKDF2 (RFC 8018) with HMAC over any supported hash.
+pub fn pbkdf2(algorithm: HashAlgorithm, password: &[u8], salt: &[u8], iterations: u32, length: usize) -> Vec<u8> {
+    let mut hmac = Hmac::new(algorithm, password);
+    let mut output = Vec::with_capacity(length);
+    let mut block_index = 1u32;
+
+    while output.len() < length {
+        hmac.update(salt);
+        hmac.update(&block_index.to_be_bytes());
+        let mut u = hmac.finish();
+        let mut block = u.clone();
+
+        for _ in 1..iterations {
+            hmac.update(&u);
+            u = hmac.finish();
+            for (b, x) in block.iter_mut().zip(u.iter()) {
+                *b ^= x;
+            }
+        }
+
+        let remaining = length - output.len();
+        output.extend_from_slice(&block[..block.len().min(remaining)]);
+        block_index += 1;
+    }
+
+    output
+}
+
+/// A salted password hash, read from and written as a PHC string such as
+/// `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`, with the salt and hash in unpadded base64.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordHash {
+    pub params: PasswordHashParams,
+    pub salt: Vec<u8>,
+    pub hash: Vec<u8>,
+}
+
+impl PasswordHash {
+    pub fn new(password: &str, params: PasswordHashParams) -> Result<Self, PasswordHashError> {
+        let mut salt = vec![0; SALT_LEN];
+        getrandom::getrandom(&mut salt).map_err(|_| PasswordHashError::RandomnessUnavailable)?;
+        Self::with_salt(password, params, &salt)
+    }
+
+    /// Fails with `InvalidParameters` for costs over the limits, since the hash couldn't be
+    /// verified afterwards.
+    pub fn with_salt(password: &str, params: PasswordHashParams, salt: &[u8]) -> Result<Self, PasswordHashError> {
+        if !params.within_limits() {
+            return Err(PasswordHashError::InvalidParameters);
+        }
+        Ok(Self {
+            params,
+            salt: salt.to_vec(),
+            hash: params.derive(password.as_bytes(), salt, HASH_LEN)?,
+        })
+    }
+
+    /// Rehashes `password` with this hash's parameters and salt, and compares the result in
+    /// constant time.
+    pub fn verify(&self, password: &str) -> Result<bool, PasswordHashError> {
+        let hash = self.params.derive(password.as_bytes(), &self.salt, self.hash.len())?;
+        Ok(constant_time_eq(&hash, &self.hash))
+    }
+}
+
+impl fmt::Display for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${}$", self.params.id())?;
+        match self.params {
+            PasswordHashParams::Pbkdf2 { iterations, .. } => write!(f, "i={}", iterations)?,
+            PasswordHashParams::Scrypt { log_n, r, p } => write!(f, "ln={},r={},p={}", log_n, r, p)?,
+            PasswordHashParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => write!(
+                f,
+                "v={}$m={},t={},p={}",
+                ARGON2_VERSION, memory_kib, iterations, parallelism
+            )?,
+        }
+        write!(
+            f,
+            "${}${}",
+            base64::encode_config(&self.salt, base64::STANDARD_NO_PAD),
+            base64::encode_config(&self.hash, base64::STANDARD_NO_PAD)
+        )
+    }
+}
+
+/// Reads `names` from a comma separated `name=value` list, which must contain exactly those
+/// parameters in that order.
+fn parse_parameters<T: FromStr>(parameters: &str, names: &[&str]) -> Result<Vec<T>, PasswordHashError> {
+    let pairs: Vec<&str> = parameters.split(',').collect();
+    if pairs.len() != names.len() {
+        return Err(PasswordHashError::InvalidFormat);
+    }
+
+    pairs
+        .iter()
+        .zip(names.iter())
+        .map(|(pair, name)| {
+            let mut split = pair.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(key), Some(value)) if key == *name => {
+                    value.parse().map_err(|_| PasswordHashError::InvalidParameters)
+                }
+                _ => Err(PasswordHashError::InvalidFormat),
+            }
+        })
+        .collect()
+}
+
+impl FromStr for PasswordHash {
+    type Err = PasswordHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields: Vec<&str> = s.split('$').collect();
+        if fields.len() < 5 || !fields[0].is_empty() {
+            return Err(PasswordHashError::InvalidFormat);
+        }
+        let id = fields[1];
+
+        let params = if let Some(algorithm) = id.strip_prefix("pbkdf2-") {
+            let algorithm = algorithm
+                .parse()
+                .map_err(|_| PasswordHashError::UnsupportedAlgorithm(String::from(id)))?;
+            let values = parse_parameters(fields[2], &["i"])?;
+            PasswordHashParams::Pbkdf2 {
+                algorithm,
+                iterations: values[0],
+            }
+        } else if id == "scrypt" {
+            let values: Vec<u32> = parse_parameters(fields[2], &["ln", "r", "p"])?;
+            PasswordHashParams::Scrypt {
+                log_n: u8::try_from(values[0]).map_err(|_| PasswordHashError::InvalidParameters)?,
+                r: values[1],
+                p: values[2],
+            }
+        } else if id == "argon2id" {
+            // The version field is the only one that comes before the parameters, and is left out
+            // for version 0x10
+            let version = if fields[2].starts_with("v=") {
+                parse_parameters::<u32>(fields.remove(2), &["v"])?[0]
+            } else {
+                ARGON2_DEFAULT_VERSION
+            };
+            if version != ARGON2_VERSION {
+                return Err(PasswordHashError::UnsupportedVersion(version));
+            }
+            if fields.len() < 5 {
+                return Err(PasswordHashError::InvalidFormat);
+            }
+            let values = parse_parameters(fields[2], &["m", "t", "p"])?;
+            PasswordHashParams::Argon2id {
+                memory_kib: values[0],
+                iterations: values[1],
+                parallelism: values[2],
+            }
+        } else {
+            return Err(PasswordHashError::UnsupportedAlgorithm(String::from(id)));
+        };
+        if !params.within_limits() {
+            return Err(PasswordHashError::InvalidParameters);
+        }
+
+        if fields.len() != 5 {
+            return Err(PasswordHashError::InvalidFormat);
+        }
+        let decode = |field: &str| {
+            base64::decode_config(field, base64::STANDARD_NO_PAD).map_err(|_| PasswordHashError::InvalidFormat)
+        };
+        let (salt, hash) = (decode(fields[3])?, decode(fields[4])?);
+        if hash.is_empty() {
+            return Err(PasswordHashError::InvalidFormat);
+        }
+
+        Ok(Self { params, salt, hash })
+    }
+}
+
+/// Hashes `password` with a random salt, returning a PHC string to store.
+pub fn hash_password(password: &str, params: PasswordHashParams) -> Result<String, PasswordHashError> {
+    Ok(PasswordHash::new(password, params)?.to_string())
+}
+
+/// Checks `password` against a PHC string produced by `hash_password` or another PHC-compatible
+/// implementation.
+pub fn verify_password(password: &str, phc: &str) -> Result<bool, PasswordHashError> {
+    phc.parse::<PasswordHash>()?.verify(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn pbkdf2_test_vectors() {
+        // RFC 6070 for SHA-1
+        let vectors = [
+            (HashAlgorithm::SHA1, 1, 20, "0c60c80f961f0e71f3a9b524af6012062fe037a6"),
+            (HashAlgorithm::SHA1, 2, 20, "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957"),
+            (
+                HashAlgorithm::SHA1,
+                4096,
+                20,
+                "4b007901b765489abead49d926f721d065a429c1",
+            ),
+            (
+                HashAlgorithm::SHA256,
+                1,
+                32,
+                "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+            ),
+            (
+                HashAlgorithm::SHA512,
+                2,
+                64,
+                "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53c\
+                f76cab2868a39b9f7840edce4fef5a82be67335c77a6068e04112754f27ccf4e",
+            ),
+        ];
+
+        for (algorithm, iterations, length, expected) in vectors.iter() {
+            assert_eq!(
+                hex::encode(pbkdf2(*algorithm, b"password", b"salt", *iterations, *length)),
+                *expected
+            );
+        }
+
+        // Output spanning several blocks
+        assert_eq!(
+            hex::encode(pbkdf2(
+                HashAlgorithm::SHA1,
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                25
+            )),
+            "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038"
+        );
+    }
+
+    #[test]
+    pub fn known_phc_strings() {
+        let hashes = [
+            "$argon2id$v=19$m=256,t=2,p=2$c29tZXNhbHQ$bQk8UB/VmZZF4Oo79iDXuL5/0ttZwg2f/5U52iv1cDc",
+            "$scrypt$ln=4,r=8,p=1$TmFDbA$9d+zly55CLIkEMXF83iJB829GnmXGxIndQK9Snfm1dM",
+            "$pbkdf2-sha256$i=1$c2FsdA$Eg+2z/z4syxD5yJSVsT4N6hlSMkszDVICAWYfLcL4Xs",
+        ];
+
+        for phc in hashes.iter() {
+            assert_eq!(verify_password("password", phc), Ok(true), "{}", phc);
+            assert_eq!(verify_password("Password", phc), Ok(false), "{}", phc);
+            assert_eq!(phc.parse::<PasswordHash>().unwrap().to_string(), *phc);
+        }
+    }
+
+    #[test]
+    pub fn random_salts() {
+        let params = [
+            PasswordHashParams::Pbkdf2 {
+                algorithm: HashAlgorithm::SHA512,
+                iterations: 10,
+            },
+            PasswordHashParams::Scrypt { log_n: 4, r: 8, p: 1 },
+            PasswordHashParams::Argon2id {
+                memory_kib: 64,
+                iterations: 1,
+                parallelism: 1,
+            },
+        ];
+
+        for &params in params.iter() {
+            let first = hash_password("hunter2", params).unwrap();
+            let second = hash_password("hunter2", params).unwrap();
+            assert_ne!(first, second);
+            assert!(verify_password("hunter2", &first).unwrap());
+            assert!(verify_password("hunter2", &second).unwrap());
+            assert!(!verify_password("hunter3", &first).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn invalid_phc_strings() {
+        assert_eq!(
+            verify_password("password", "$bcrypt$x$c2FsdA$aGFzaA"),
+            Err(PasswordHashError::UnsupportedAlgorithm(String::from("bcrypt")))
+        );
+        assert_eq!(
+            verify_password("password", "$argon2id$v=16$m=256,t=2,p=2$c29tZXNhbHQ$aGFzaA"),
+            Err(PasswordHashError::UnsupportedVersion(16))
+        );
+        // No version field means 0x10, not a missing field
+        assert_eq!(
+            verify_password("password", "$argon2id$m=256,t=2,p=2$c29tZXNhbHQ$aGFzaA"),
+            Err(PasswordHashError::UnsupportedVersion(16))
+        );
+        assert_eq!(
+            verify_password("password", "$argon2id$v=19$c29tZXNhbHQ$aGFzaA"),
+            Err(PasswordHashError::InvalidFormat)
+        );
+        assert_eq!(
+            verify_password("password", "$scrypt$ln=4,p=1$TmFDbA$aGFzaA"),
+            Err(PasswordHashError::InvalidFormat)
+        );
+        assert_eq!(
+            verify_password("password", "$pbkdf2-sha256$i=many$c2FsdA$aGFzaA"),
+            Err(PasswordHashError::InvalidParameters)
+        );
+        assert_eq!(
+            verify_password("password", "pbkdf2-sha256$i=1$c2FsdA$aGFzaA"),
+            Err(PasswordHashError::InvalidFormat)
+        );
+        assert_eq!(
+            verify_password("password", "$pbkdf2-sha256$i=1$c2FsdA$!!"),
+            Err(PasswordHashError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    pub fn cost_limits() {
+        let over_limits = [
+            "$pbkdf2-sha256$i=10000001$c2FsdA$aGFzaA",
+            "$pbkdf2-sha256$i=4294967295$c2FsdA$aGFzaA",
+            // Too big for a u8, rather than read as ln=4
+            "$scrypt$ln=260,r=8,p=1$TmFDbA$aGFzaA",
+            "$scrypt$ln=21,r=1,p=1$TmFDbA$aGFzaA",
+            "$scrypt$ln=4,r=33,p=1$TmFDbA$aGFzaA",
+            "$scrypt$ln=4,r=8,p=17$TmFDbA$aGFzaA",
+            // Every parameter allowed on its own, but 128 * 16 * 2^20 bytes together
+            "$scrypt$ln=20,r=16,p=1$TmFDbA$aGFzaA",
+            "$argon2id$v=19$m=1048577,t=2,p=1$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=4294967295,t=2,p=1$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=256,t=33,p=1$c29tZXNhbHQ$aGFzaA",
+            "$argon2id$v=19$m=256,t=2,p=17$c29tZXNhbHQ$aGFzaA",
+        ];
+        for phc in over_limits.iter() {
+            assert_eq!(
+                verify_password("password", phc),
+                Err(PasswordHashError::InvalidParameters),
+                "{}",
+                phc
+            );
+        }
+
+        // The limits themselves are allowed
+        let at_limits = [
+            "$pbkdf2-sha256$i=10000000$c2FsdA$aGFzaA",
+            "$scrypt$ln=20,r=8,p=16$TmFDbA$aGFzaA",
+            "$argon2id$v=19$m=1048576,t=32,p=16$c29tZXNhbHQ$aGFzaA",
+        ];
+        for phc in at_limits.iter() {
+            assert!(phc.parse::<PasswordHash>().is_ok(), "{}", phc);
+        }
+
+        assert_eq!(
+            PasswordHash::with_salt(
+                "password",
+                PasswordHashParams::Scrypt { log_n: 30, r: 8, p: 1 },
+                b"salt"
+            ),
+            Err(PasswordHashError::InvalidParameters)
+        );
+        assert!(PasswordHashParams::PBKDF2.within_limits());
+        assert!(PasswordHashParams::SCRYPT.within_limits());
+        assert!(PasswordHashParams::ARGON2ID.within_limits());
+    }
+}
@@ -0,0 +1,295 @@
+//! SHA-1 (FIPS 180-4) with its internal state exposed, for crackers and attacks that need more
+//! than a hex digest: hashing a shared prefix once and cloning the state for each candidate,
+//! comparing digests as five words, or resuming from a known state.
+
+pub const BLOCK_LEN: usize = 64;
+pub const DIGEST_LEN: usize = 20;
+
+pub const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+const K: [u32; 4] = [0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xca62c1d6];
+
+/// Runs the compression function over one 64 byte block, updating `state` in place.
+pub fn compress(state: &mut [u32; 5], block: &[u8; BLOCK_LEN]) {
+    let mut w = [0u32; 80];
+    for (i, word) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, &word) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), K[0]),
+            20..=39 => (b ^ c ^ d, K[1]),
+            40..=59 => ((b & c) | (b & d) | (c & d), K[2]),
+            _ => (b ^ c ^ d, K[3]),
+        };
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+        *s = s.wrapping_add(*v);
+    }
+}
+
+/// The padding SHA-1 appends to a message of `length` bytes: a one bit, zeros up to 56 mod 64,
+/// then the length in bits.
+pub fn padding(length: u64) -> Vec<u8> {
+    let mut padding = vec![0x80];
+    padding.resize(1 + (BLOCK_LEN * 2 - 9 - length as usize % BLOCK_LEN) % BLOCK_LEN, 0);
+    padding.extend_from_slice(&(length.wrapping_mul(8)).to_be_bytes());
+    padding
+}
+
+/// An incremental SHA-1. Cloning a hasher that has been fed a prefix gives the state after that
+/// prefix, so it only has to be hashed once.
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [u32; 5],
+    buffer: [u8; BLOCK_LEN],
+    buffer_len: usize,
+    length: u64,
+}
+
+impl Sha1 {
+    pub fn new() -> Self {
+        Self {
+            state: INITIAL_STATE,
+            buffer: [0; BLOCK_LEN],
+            buffer_len: 0,
+            length: 0,
+        }
+    }
+
+    /// Resumes hashing from `state`, as if `length` bytes had already been hashed. `None` unless
+    /// `length` is a whole number of blocks, since a state is only ever produced at a block
+    /// boundary.
+    pub fn from_state(state: [u32; 5], length: u64) -> Option<Self> {
+        if length % BLOCK_LEN as u64 != 0 {
+            return None;
+        }
+        Some(Self {
+            state,
+            length,
+            ..Self::new()
+        })
+    }
+
+    /// The chaining state after the last complete block. Bytes still buffered aren't included.
+    pub fn state(&self) -> [u32; 5] {
+        self.state
+    }
+
+    /// The number of bytes hashed so far.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+
+        if self.buffer_len > 0 {
+            let take = data.len().min(BLOCK_LEN - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+            if self.buffer_len < BLOCK_LEN {
+                return;
+            }
+            let block = self.buffer;
+            compress(&mut self.state, &block);
+            self.buffer_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(BLOCK_LEN);
+        for block in &mut blocks {
+            let mut full = [0u8; BLOCK_LEN];
+            full.copy_from_slice(block);
+            compress(&mut self.state, &full);
+        }
+
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// Pads the message and returns the digest as words, leaving the hasher untouched so more
+    /// data can still be added. The padding is written straight into the last one or two blocks,
+    /// so finishing doesn't allocate.
+    pub fn finish(&self) -> [u32; 5] {
+        let mut state = self.state;
+        let mut block = [0u8; BLOCK_LEN];
+        block[..self.buffer_len].copy_from_slice(&self.buffer[..self.buffer_len]);
+        block[self.buffer_len] = 0x80;
+        // No room left for the length, so it goes in a block of its own
+        if self.buffer_len >= BLOCK_LEN - 8 {
+            compress(&mut state, &block);
+            block = [0; BLOCK_LEN];
+        }
+        block[BLOCK_LEN - 8..].copy_from_slice(&self.length.wrapping_mul(8).to_be_bytes());
+        compress(&mut state, &block);
+        state
+    }
+
+    pub fn finish_bytes(&self) -> [u8; DIGEST_LEN] {
+        to_bytes(self.finish())
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn sha1(data: &[u8]) -> [u32; 5] {
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+pub fn to_bytes(digest: [u32; 5]) -> [u8; DIGEST_LEN] {
+    let mut bytes = [0u8; DIGEST_LEN];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(digest.iter()) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Option<[u32; 5]> {
+    if bytes.len() != DIGEST_LEN {
+        return None;
+    }
+    let mut digest = [0u32; 5];
+    for (word, chunk) in digest.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    Some(digest)
+}
+
+pub fn to_hex(digest: [u32; 5]) -> String {
+    hex::encode(to_bytes(digest))
+}
+
+/// Parses a 40 character hex digest, upper or lower case.
+pub fn from_hex(digest: &str) -> Option<[u32; 5]> {
+    from_bytes(&hex::decode(digest).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn nist_test_vectors() {
+        let vectors = [
+            ("", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            ("abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+            (
+                "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrs\
+                mnopqrstnopqrstu",
+                "a49b2446a02c645bf419f995b67091253a04a259",
+            ),
+        ];
+        for (input, digest) in vectors.iter() {
+            assert_eq!(to_hex(sha1(input.as_bytes())), *digest);
+        }
+
+        let million_a = vec![b'a'; 1_000_000];
+        assert_eq!(to_hex(sha1(&million_a)), "34aa973cd4c4daa4f61eeb2bdbad27316534016f");
+    }
+
+    #[test]
+    pub fn matches_sha1_crate_across_block_boundaries() {
+        let input: Vec<u8> = (0..200u8).collect();
+        for length in 0..input.len() {
+            let mut reference = ::sha1::Sha1::new();
+            reference.update(&input[..length]);
+            assert_eq!(
+                to_bytes(sha1(&input[..length])),
+                reference.digest().bytes(),
+                "{}",
+                length
+            );
+
+            // Fed in uneven pieces
+            let mut hasher = Sha1::new();
+            for piece in input[..length].chunks(7) {
+                hasher.update(piece);
+            }
+            assert_eq!(hasher.finish_bytes(), reference.digest().bytes(), "{}", length);
+        }
+    }
+
+    #[test]
+    pub fn matches_kernel_constants() {
+        // The OpenCL kernel carries its own SHA-1. This only catches its constants drifting, and
+        // runs without a device; the gpu module's tests check the digests it actually computes
+        let kernel = include_str!("cracker/kernel.cl");
+        let constant = |name: &str| -> u32 {
+            let line = kernel.lines().find(|line| line.trim().starts_with(name)).unwrap();
+            let value = line.split('=').nth(1).unwrap().trim().trim_end_matches(';');
+            u32::from_str_radix(value.trim_start_matches("0x"), 16).unwrap()
+        };
+
+        for (i, &word) in INITIAL_STATE.iter().enumerate() {
+            assert_eq!(constant(&format!("ctx->state[{}] =", i)), word);
+        }
+        for (i, &k) in K.iter().enumerate() {
+            assert_eq!(constant(&format!("ctx->k[{}] =", i)), k);
+        }
+    }
+
+    #[test]
+    pub fn prefix_state_reuse() {
+        let mut prefix = Sha1::new();
+        prefix.update(&[b'x'; 100]);
+
+        for suffix in ["", "a", "password", "0123456789"].iter() {
+            let mut hasher = prefix.clone();
+            hasher.update(suffix.as_bytes());
+            let mut message = vec![b'x'; 100];
+            message.extend_from_slice(suffix.as_bytes());
+            assert_eq!(hasher.finish(), sha1(&message));
+        }
+
+        // Resuming from the state after one block
+        let mut one_block = Sha1::new();
+        one_block.update(&[b'x'; 64]);
+        let mut resumed = Sha1::from_state(one_block.state(), 64).unwrap();
+        resumed.update(&[b'x'; 36]);
+        assert_eq!(resumed.finish(), prefix.finish());
+        assert_eq!(resumed.len(), 100);
+        assert!(Sha1::from_state(one_block.state(), 63).is_none());
+    }
+
+    #[test]
+    pub fn digest_conversions() {
+        let digest = sha1(b"hello");
+        assert_eq!(to_hex(digest), "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d");
+        assert_eq!(from_hex("AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D"), Some(digest));
+        assert_eq!(from_bytes(&to_bytes(digest)), Some(digest));
+        assert_eq!(from_hex("aaf4c61d"), None);
+        assert_eq!(from_hex("not hex"), None);
+    }
+}
@@ -1,9 +1,27 @@
 mod crypto;
 
+pub use crate::crypto::analysis;
 pub use crate::crypto::bch;
+pub use crate::crypto::cardgen;
+pub use crate::crypto::checkdigit;
+pub use crate::crypto::collision;
 pub use crate::crypto::cracker::cpu;
+pub use crate::crypto::cracker::dictionary;
 pub use crate::crypto::cracker::gpu;
+pub use crate::crypto::cracker::handle;
+pub use crate::crypto::cracker::job;
+pub use crate::crypto::cracker::mask;
+pub use crate::crypto::cracker::rules;
 pub use crate::crypto::credit;
+pub use crate::crypto::fpe;
 pub use crate::crypto::hamming;
 pub use crate::crypto::hash;
+pub use crate::crypto::hkdf;
+pub use crate::crypto::hmac;
+pub use crate::crypto::iban;
+pub use crate::crypto::identify;
 pub use crate::crypto::isbn;
+pub use crate::crypto::length_extension;
+pub use crate::crypto::merkle;
+pub use crate::crypto::password;
+pub use crate::crypto::sha1;
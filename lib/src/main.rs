@@ -2,24 +2,35 @@ use actix_cors::Cors;
 use actix_web::middleware::Logger;
 use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use crypto::credit::CreditCardVerificationError;
+use crypto::hash::HashAlgorithm;
 use crypto::isbn::ISBNVerificationError;
+use crypto::job::{CrackJobBuilder, CrackResult};
 
 async fn isbn(req: HttpRequest) -> impl Responder {
     let x: String = req.match_info().get("isbn").unwrap().parse().unwrap();
     let valid = match crypto::isbn::verify_isbn(&x) {
-        Ok(_) => String::from("ISBN is valid!"),
+        Ok(format) => format!("{} is valid!", format),
         Err(err) => match err {
             ISBNVerificationError::InvalidDigitCount => String::from("ISBN has wrong number of digits."),
-            ISBNVerificationError::NonValidISBN => String::from("ISBN is invalid."),
-            ISBNVerificationError::InvalidDigitsFound => String::from("ISBN has invalid characters present."),
+            ISBNVerificationError::NonValidISBN(format) => format!("{} is invalid.", format),
+            ISBNVerificationError::InvalidDigitsFound(format) => {
+                format!("{} has invalid characters present.", format)
+            }
+            ISBNVerificationError::InvalidPrefix => String::from("ISBN-13 must start with 978 or 979."),
+            ISBNVerificationError::NotConvertible => String::from("ISBN has no ISBN-10 equivalent."),
+            ISBNVerificationError::UnknownRange => String::from("ISBN is outside the known ISBN ranges."),
         },
     };
     HttpResponse::Ok().body(valid)
 }
 
 async fn ccn(req: HttpRequest) -> impl Responder {
-    let valid = match crypto::credit::verify_credit_card(req.match_info().get("ccn").unwrap()) {
-        Ok(_) => String::from("Credit card number is valid!"),
+    let input = req.match_info().get("ccn").unwrap();
+    let valid = match crypto::credit::verify_credit_card(input) {
+        Ok(_) => format!(
+            "Credit card number is valid! ({})",
+            crypto::credit::identify_card(input).scheme
+        ),
         Err(err) => match err {
             CreditCardVerificationError::InvalidCreditCard => String::from("Credit card number is not valid"),
             CreditCardVerificationError::InvalidDigitsFound => String::from("Credit card number has invalid digits"),
@@ -29,6 +40,14 @@ async fn ccn(req: HttpRequest) -> impl Responder {
     HttpResponse::Ok().body(valid)
 }
 
+async fn iban(req: HttpRequest) -> impl Responder {
+    let input = req.match_info().get("iban").unwrap();
+    HttpResponse::Ok().body(match crypto::iban::parse_iban(input) {
+        Ok(iban) => format!("{} is a valid IBAN!", iban),
+        Err(error) => error.to_string(),
+    })
+}
+
 async fn hamming_check_digits(req: HttpRequest) -> impl Responder {
     let input = req.match_info().get("input").unwrap();
     HttpResponse::Ok().body(match crypto::hamming::calculate_hamming_check_digits(input) {
@@ -58,9 +77,33 @@ async fn sha(req: HttpRequest) -> impl Responder {
     HttpResponse::Ok().body(crypto::hash::sha1(input))
 }
 
+async fn hash(req: HttpRequest) -> impl Responder {
+    let input = req.match_info().get("input").unwrap();
+    let algorithm = req.match_info().get("algorithm").unwrap();
+    match algorithm.parse::<HashAlgorithm>() {
+        Ok(algorithm) => HttpResponse::Ok().body(algorithm.hex_digest(input.as_bytes())),
+        Err(error) => HttpResponse::BadRequest().body(error.to_string()),
+    }
+}
+
+async fn identify(req: HttpRequest) -> impl Responder {
+    let input = req.match_info().get("hash").unwrap();
+    let candidates: Vec<String> = crypto::identify::identify(input)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    HttpResponse::Ok().json(candidates)
+}
+
 use futures_util::StreamExt;
 
 const MAX_SIZE: usize = 262_144;
+
+/// The body of a failed cracking request, `{"error": "..."}`, so the page can always parse it.
+fn error_body<E: ToString>(error: E) -> serde_json::Value {
+    serde_json::json!({ "error": error.to_string() })
+}
+
 async fn crack_normal(mut payload: web::Payload) -> impl Responder {
     let mut body = web::BytesMut::new();
     while let Some(chunk) = payload.next().await {
@@ -72,14 +115,45 @@ async fn crack_normal(mut payload: web::Payload) -> impl Responder {
     }
 
     match serde_json::from_slice::<Vec<String>>(&body) {
-        Ok(hashes) => HttpResponse::Ok().json(
-            match crypto::gpu::crack(&hashes.iter().map(std::ops::Deref::deref).collect::<Vec<&str>>()) {
-                None => Vec::new(),
-                Some(passwords) => passwords,
-            },
-        ),
-        Err(_) => HttpResponse::BadRequest().finish(),
+        Ok(hashes) => match web::block(move || crack_hashes(&hashes)).await {
+            Ok(results) => HttpResponse::Ok().json(results),
+            Err(error) => HttpResponse::InternalServerError().json(error_body(error)),
+        },
+        Err(error) => HttpResponse::BadRequest().json(error_body(error)),
+    }
+}
+
+/// Cracks `hashes`, which can take a while, so it runs on the blocking thread pool. The hashes are
+/// identified and grouped by algorithm, and each group is searched for in a single job: SHA-1 on
+/// the GPU, anything else on the CPU. Hashes that can't be identified are reported as not found.
+/// Fails if the GPU can't run its share.
+fn crack_hashes(hashes: &[String]) -> Result<Vec<CrackResult>, &'static str> {
+    let mut groups: Vec<(HashAlgorithm, Vec<usize>)> = Vec::new();
+    for (i, hash) in hashes.iter().enumerate() {
+        if let Some((_, algorithm, _)) = crypto::identify::unsalted_digest(hash) {
+            match groups.iter_mut().find(|(grouped, _)| *grouped == algorithm) {
+                Some((_, positions)) => positions.push(i),
+                None => groups.push((algorithm, vec![i])),
+            }
+        }
+    }
+
+    let mut results: Vec<CrackResult> = hashes.iter().map(|hash| CrackResult::new(hash)).collect();
+    for (algorithm, positions) in groups {
+        let targets: Vec<&str> = positions.iter().map(|&i| hashes[i].as_str()).collect();
+        let cracked = if algorithm == HashAlgorithm::SHA1 {
+            crypto::gpu::crack(&targets).ok_or("GPU cracking failed")?
+        } else {
+            match CrackJobBuilder::new().algorithm(algorithm).targets(targets).build() {
+                Ok(job) => crypto::cpu::crack(&job),
+                Err(_) => continue,
+            }
+        };
+        for (i, result) in positions.into_iter().zip(cracked) {
+            results[i] = result;
+        }
     }
+    Ok(results)
 }
 
 async fn crack_bch(mut payload: web::Payload) -> impl Responder {
@@ -93,13 +167,18 @@ async fn crack_bch(mut payload: web::Payload) -> impl Responder {
     }
 
     match serde_json::from_slice::<Vec<String>>(&body) {
-        Ok(hashes) => HttpResponse::Ok().json(
-            match crypto::cpu::crack_bch(&hashes.iter().map(std::ops::Deref::deref).collect::<Vec<&str>>()) {
-                None => Vec::new(),
-                Some(passwords) => passwords,
-            },
-        ),
-        Err(_) => HttpResponse::BadRequest().finish(),
+        Ok(hashes) => {
+            let cracked = web::block(move || {
+                Ok::<_, ()>(crypto::cpu::crack_bch(
+                    &hashes.iter().map(std::ops::Deref::deref).collect::<Vec<&str>>(),
+                ))
+            });
+            match cracked.await {
+                Ok(results) => HttpResponse::Ok().json(results),
+                Err(error) => HttpResponse::InternalServerError().json(error_body(error)),
+            }
+        }
+        Err(error) => HttpResponse::BadRequest().json(error_body(error)),
     }
 }
 
@@ -113,10 +192,13 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::default())
             .service(web::resource("/isbn/{isbn}").route(web::get().to(isbn)))
             .service(web::resource("/ccn/{ccn}").route(web::get().to(ccn)))
+            .service(web::resource("/iban/{iban}").route(web::get().to(iban)))
             .service(web::resource("/hamming/checkdigits/{input}").route(web::get().to(hamming_check_digits)))
             .service(web::resource("/hamming/syndromes/{input}").route(web::get().to(hamming_syndrome_vector)))
             .service(web::resource("/bch/{bch}").route(web::get().to(bch)))
             .service(web::resource("/hash/{input}").route(web::get().to(sha)))
+            .service(web::resource("/hash/{algorithm}/{input}").route(web::get().to(hash)))
+            .service(web::resource("/identify/{hash:.*}").route(web::get().to(identify)))
             .service(web::resource("/crack/").route(web::post().to(crack_normal)))
             .service(web::resource("/crackbch/").route(web::post().to(crack_bch)))
             .wrap(cors)
@@ -2,6 +2,7 @@ import React from "react";
 
 const Week4: React.FC = () => {
     const [input, setInput] = React.useState("");
+    const [algorithm, setAlgorithm] = React.useState("sha1");
     const [hash, setHash] = React.useState("");
 
     const ref = React.useRef<HTMLFormElement>(null);
@@ -10,9 +11,13 @@ const Week4: React.FC = () => {
         setInput(e.target.value);
     }
 
+    const handleAlgorithmChange = (e: any) => {
+        setAlgorithm(e.target.value);
+    }
+
     const submitForHashing = (e: any) => {
         e.preventDefault();
-        fetch(`http://127.0.0.1:8080/hash/${input}`).then((response) => {
+        fetch(`http://127.0.0.1:8080/hash/${algorithm}/${input}`).then((response) => {
             response.text().then((text) => {
                 setHash(text);
             });
@@ -29,13 +34,17 @@ const Week4: React.FC = () => {
     return (
         <div>
             <h1>Password Encryption</h1>
-            <p>Encrypt a given password using SHA1</p>
+            <p>Hash a given password using the selected algorithm</p>
 
             <form onSubmit={submitForHashing}>
                 <label>
                     <input className={"form-field animation"} required={true} onChange={handleInputChange}
                         placeholder={"Input to hash"} value={input}/>
                 </label>
+                <select className={"form-field"} onChange={handleAlgorithmChange} value={algorithm}>
+                    {["md5", "sha1", "sha224", "sha256", "sha384", "sha512", "sha3-256", "blake2b", "blake3"]
+                        .map((name) => <option key={name} value={name}>{name}</option>)}
+                </select>
                 <button className={"form-button"}>Hash input</button>
                 <button type={"reset"} className={"form-button"} onClick={clear}>Clear</button>
             </form>
@@ -1,10 +1,20 @@
 import React from "react";
 
+interface CrackResult {
+    hash: string;
+    plaintext: string | null;
+    elapsed: { secs: number, nanos: number };
+    candidates_tried: number;
+}
+
+const seconds = (result: CrackResult) => (result.elapsed.secs + result.elapsed.nanos / 1e9).toFixed(2);
+
 const Week5: React.FC = () => {
     const [hashes, setHashes] = React.useState("");
     const [bchHashes, setBchHashes] = React.useState("");
-    const [passwords, setPasswords] = React.useState<string[]>([]);
-    const [bchCodes, setBchCodes] = React.useState<string[]>([]);
+    const [passwords, setPasswords] = React.useState<CrackResult[]>([]);
+    const [bchCodes, setBchCodes] = React.useState<CrackResult[]>([]);
+    const [error, setError] = React.useState("");
 
     const ref = React.useRef<HTMLFormElement>(null);
 
@@ -20,10 +30,15 @@ const Week5: React.FC = () => {
         e.preventDefault();
         let body = hashes.split('\n');
         setPasswords([]);
+        setError("");
         fetch(`http://127.0.0.1:8080/crack/`, {body: JSON.stringify(body), method: "POST"}).then((response) => {
             response.json().then((json) => {
-                setPasswords(json);
-            });
+                if (response.ok) {
+                    setPasswords(json);
+                } else {
+                    setError(json.error);
+                }
+            }).catch(() => setError(`Cracking failed: ${response.status} ${response.statusText}`));
         });
     }
 
@@ -31,10 +46,15 @@ const Week5: React.FC = () => {
         e.preventDefault();
         let body = bchHashes.split('\n');
         setBchCodes([]);
+        setError("");
         fetch(`http://127.0.0.1:8080/crackbch/`, {body: JSON.stringify(body), method: "POST"}).then((response) => {
             response.json().then((json) => {
-                setBchCodes(json);
-            });
+                if (response.ok) {
+                    setBchCodes(json);
+                } else {
+                    setError(json.error);
+                }
+            }).catch(() => setError(`Cracking failed: ${response.status} ${response.statusText}`));
         });
     }
 
@@ -42,6 +62,7 @@ const Week5: React.FC = () => {
         e.preventDefault();
         setHashes("");
         setPasswords([]);
+        setError("");
 
         ref.current?.reset();
     }
@@ -50,31 +71,24 @@ const Week5: React.FC = () => {
         e.preventDefault();
         setBchHashes("");
         setBchCodes([]);
+        setError("");
     }
 
-    let password_output_lines = [];
-    if (passwords.length !== 0) {
-        let hash_list = hashes.split('\n');
-        for (let i = 0; i < passwords.length; i++) {
-            password_output_lines.push(
-                <tr key={i}>
-                    <td>{hash_list[i]}</td>
-                    <td>{(passwords[i].length > 0) ? passwords[i] : "Not Found"}</td>
-                </tr>);
-        }
-    }
+    let password_output_lines = passwords.map((result, i) =>
+        <tr key={i}>
+            <td>{result.hash}</td>
+            <td>{result.plaintext ?? "Not Found"}</td>
+            <td>{result.candidates_tried}</td>
+            <td>{seconds(result)}s</td>
+        </tr>);
 
-    let bch_output_lines = [];
-    if (bchCodes.length !== 0) {
-        let hash_list = bchHashes.split('\n');
-        for (let i = 0; i < bchCodes.length; i++) {
-            bch_output_lines.push(
-                <tr key={i}>
-                    <td>{hash_list[i]}</td>
-                    <td>{(bchCodes[i].length > 0) ? bchCodes[i] : "Not Found"}</td>
-                </tr>);
-        }
-    }
+    let bch_output_lines = bchCodes.map((result, i) =>
+        <tr key={i}>
+            <td>{result.hash}</td>
+            <td>{result.plaintext ?? "Not Found"}</td>
+            <td>{result.candidates_tried}</td>
+            <td>{seconds(result)}s</td>
+        </tr>);
 
     return (
         <div>
@@ -106,12 +120,15 @@ const Week5: React.FC = () => {
                     </form>
                 </div>
             </div>
+            {error !== "" ? <p>{error}</p> : ""}
             <div style={{display: "flex", justifyItems: "center", marginTop: "5rem"}}>
                 {password_output_lines.length !== 0 ? (
                     <table>
                         <tr>
                             <th>Hash</th>
                             <th>Password</th>
+                            <th>Candidates</th>
+                            <th>Time</th>
                         </tr>
                         {password_output_lines}
                     </table>
@@ -123,6 +140,8 @@ const Week5: React.FC = () => {
                         <tr>
                             <th>Hash</th>
                             <th>BCH Input</th>
+                            <th>Candidates</th>
+                            <th>Time</th>
                         </tr>
                         {bch_output_lines}
                     </table>